@@ -0,0 +1,124 @@
+use crate::settings::{self, JsonPhase, JsonPhaseColor, JsonPhaseSound};
+use log::warn;
+use serde::Deserialize;
+use std::cell::Cell;
+use std::thread::LocalKey;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//Extra suggestions can go in here, in the config directory next to the settings. They're added to
+//the built in ones rather than replacing them, and it's read again for every break so there's no
+//need to restart after editing it.
+//{
+//    "short": ["Roll your shoulders"],
+//    "long": ["Make a cup of tea"]
+//}
+const ACTIVITIES_FILE: &str = "activities.json";
+
+const SHORT: &[&str] = &[
+    "Stretch your neck, slowly tilting your head side to side.",
+    "Refill your water.",
+    "Look at something 20 ft away for 20 seconds.",
+    "Roll your shoulders back a few times.",
+    "Stand up and stretch your arms above your head.",
+    "Close your eyes and take five slow breaths.",
+    "Shake out your hands and stretch your wrists.",
+];
+
+const LONG: &[&str] = &[
+    "Go for a short walk.",
+    "Get some fresh air outside.",
+    "Make yourself something to eat.",
+    "Do a few minutes of stretching.",
+    "Tidy up your desk.",
+    "Step away from the screen and rest your eyes.",
+    "Call or message a friend.",
+];
+
+//Reminders part way through a focus, only ever built in
+const MICRO: &[&str] = &[
+    "20-20-20: look at something 20 ft away for 20 seconds.",
+    "Posture check: sit back, feet flat and shoulders relaxed.",
+    "Blink slowly a few times to rest your eyes.",
+    "Unclench your jaw and drop your shoulders.",
+];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonActivities {
+    short: Vec<String>,
+    long: Vec<String>,
+}
+
+//Where each pool is up to. They start somewhere different each run so it isn't always the same
+//suggestion first thing in the morning.
+thread_local! {
+    static NEXT_SHORT: Cell<Option<usize>> = const { Cell::new(None) };
+    static NEXT_LONG: Cell<Option<usize>> = const { Cell::new(None) };
+    static NEXT_MICRO: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+//A long break is whatever looks or sounds like one, which covers the classic cycle and most
+//custom sequences
+#[must_use]
+pub fn is_long(phase: &JsonPhase) -> bool {
+    phase.color == JsonPhaseColor::LongRound || phase.sound == JsonPhaseSound::LongBreak
+}
+
+//The next suggestion for a break, in turn from whichever pool it draws from
+#[must_use]
+pub fn next(long: bool) -> String {
+    let user = load();
+    let (built_in, extra, next) = if long {
+        (LONG, user.long, &NEXT_LONG)
+    } else {
+        (SHORT, user.short, &NEXT_SHORT)
+    };
+    let pool: Vec<String> = built_in
+        .iter()
+        .map(ToString::to_string)
+        .chain(
+            extra
+                .into_iter()
+                .filter(|activity| !activity.trim().is_empty()),
+        )
+        .collect();
+
+    pick(&pool, next)
+}
+
+#[must_use]
+pub fn micro() -> String {
+    let pool: Vec<String> = MICRO.iter().map(ToString::to_string).collect();
+    pick(&pool, &NEXT_MICRO)
+}
+
+fn pick(pool: &[String], next: &'static LocalKey<Cell<Option<usize>>>) -> String {
+    let idx = next.with(|next| {
+        let idx = next.get().unwrap_or_else(start) % pool.len();
+        next.set(Some(idx + 1));
+        idx
+    });
+    pool[idx].clone()
+}
+
+fn start() -> usize {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| usize::try_from(since.as_secs()).unwrap_or(0))
+}
+
+fn load() -> JsonActivities {
+    let Some(path) = settings::get_dir().map(|dir| dir.join(ACTIVITIES_FILE)) else {
+        return JsonActivities::default();
+    };
+    let Ok(text) = std::fs::read_to_string(&path) else {
+        return JsonActivities::default();
+    };
+    serde_json::from_str(&text).unwrap_or_else(|e| {
+        warn!(
+            "Unable to read the break activities in {}: {e}",
+            path.display()
+        );
+        JsonActivities::default()
+    })
+}
@@ -0,0 +1,670 @@
+use chrono::{
+    DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Utc, Weekday,
+};
+use log::{info, warn};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+//How often the calendars are looked at for a meeting starting or finishing. The files themselves
+//are only read again when they change.
+pub const CHECK: Duration = Duration::from_secs(30);
+
+//There's no time zone database to hand, so times given with a TZID are taken as local time. That's
+//right for a calendar exported in the zone it's used in, which is the usual case, and a warning is
+//logged for any other zone since those meetings will be off by the difference. Times in UTC (the
+//ones ending in Z) are converted properly.
+#[derive(Debug, Clone)]
+struct Event {
+    uid: String,
+    summary: String,
+    start: NaiveDateTime,
+    length: TimeDelta,
+    rule: Option<Rule>,
+    exdates: Vec<NaiveDateTime>,
+    //set on a single changed occurrence of a repeating event, which replaces the one it names
+    recurrence_id: Option<NaiveDateTime>,
+}
+
+//Only daily and weekly repeats are worked out, which covers most meetings. Anything else just
+//shows up on its first date.
+#[derive(Debug, Clone)]
+struct Rule {
+    weekly: bool,
+    interval: i64,
+    count: Option<usize>,
+    until: Option<NaiveDateTime>,
+    by_day: Vec<Weekday>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Meeting {
+    pub summary: String,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+impl Meeting {
+    #[must_use]
+    pub fn seconds(&self) -> i64 {
+        (self.end - self.start).num_seconds()
+    }
+}
+
+//A meeting that's been noticed starting, with what the timer was doing at the time
+#[derive(Debug, Clone)]
+pub struct Attending {
+    pub meeting: Meeting,
+    pub running: bool,
+    pub since: NaiveDateTime,
+}
+
+#[derive(Default)]
+struct Cache {
+    files: Vec<(PathBuf, Option<SystemTime>, Vec<Event>)>,
+    meetings: Vec<Meeting>,
+}
+
+//Only ever touched from the event loop
+thread_local! {
+    static CACHE: RefCell<Cache> = RefCell::new(Cache::default());
+    static ATTENDING: RefCell<Option<Attending>> = const { RefCell::new(None) };
+}
+
+fn now() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
+//Reads any of the files that changed since last time and works out the meetings from yesterday
+//to the day after tomorrow, which is as far either way as the timer ever needs to look
+pub fn refresh(paths: &[String]) {
+    CACHE.with_borrow_mut(|cache| {
+        let files = paths
+            .iter()
+            .map(|path| {
+                let path = PathBuf::from(path);
+                let modified = std::fs::metadata(&path)
+                    .and_then(|meta| meta.modified())
+                    .ok();
+                let cached = cache.files.iter().find(|(cached, when, _)| {
+                    *cached == path && *when == modified && when.is_some()
+                });
+                let events = match cached {
+                    Some((_, _, events)) => events.clone(),
+                    None => load(&path),
+                };
+                (path, modified, events)
+            })
+            .collect();
+        cache.files = files;
+
+        let now = now();
+        let events: Vec<&Event> = cache
+            .files
+            .iter()
+            .flat_map(|(_, _, events)| events)
+            .collect();
+        cache.meetings = meetings(&events, now - TimeDelta::days(1), now + TimeDelta::days(2));
+    });
+}
+
+//Every meeting from the events that overlaps from..to, in the order they start
+fn meetings(events: &[&Event], from: NaiveDateTime, to: NaiveDateTime) -> Vec<Meeting> {
+    let mut meetings: Vec<Meeting> = events
+        .iter()
+        .flat_map(|event| {
+            //the changed occurrences stand in for these ones
+            let moved: Vec<NaiveDateTime> = events
+                .iter()
+                .filter(|other| other.uid == event.uid)
+                .filter_map(|other| other.recurrence_id)
+                .collect();
+            occurrences(event, from, to)
+                .into_iter()
+                .filter(move |start| event.recurrence_id.is_some() || !moved.contains(start))
+                .map(|start| Meeting {
+                    summary: event.summary.clone(),
+                    start,
+                    end: start + event.length,
+                })
+        })
+        .collect();
+    meetings.sort_by_key(|meeting| meeting.start);
+    meetings
+}
+
+//the meeting going on right now, the one that started last if they overlap
+#[must_use]
+pub fn current() -> Option<Meeting> {
+    let now = now();
+    CACHE.with_borrow(|cache| {
+        cache
+            .meetings
+            .iter()
+            .filter(|meeting| meeting.start <= now && now < meeting.end)
+            .max_by_key(|meeting| meeting.start)
+            .cloned()
+    })
+}
+
+//the first meeting starting between now and before
+#[must_use]
+pub fn next_starting(before: NaiveDateTime) -> Option<Meeting> {
+    let now = now();
+    CACHE.with_borrow(|cache| {
+        cache
+            .meetings
+            .iter()
+            .find(|meeting| meeting.start > now && meeting.start < before)
+            .cloned()
+    })
+}
+
+pub fn attend(meeting: Meeting, running: bool) {
+    ATTENDING.set(Some(Attending {
+        meeting,
+        running,
+        since: now(),
+    }));
+}
+
+#[must_use]
+pub fn attending() -> Option<Meeting> {
+    ATTENDING.with_borrow(|attending| attending.as_ref().map(|a| a.meeting.clone()))
+}
+
+pub fn leave() -> Option<Attending> {
+    ATTENDING.take()
+}
+
+fn load(path: &PathBuf) -> Vec<Event> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => {
+            let events = parse(&text);
+            info!("Read {} events from {}", events.len(), path.display());
+            for zone in other_zones(&text) {
+                warn!(
+                    "{} has times in {zone}, which are taken as local time and may be off",
+                    path.display()
+                );
+            }
+            events
+        }
+        Err(e) => {
+            warn!("Unable to read the calendar {}: {e}", path.display());
+            Vec::new()
+        }
+    }
+}
+
+//The time zones the calendar's times are given in that aren't this computer's own
+fn other_zones(text: &str) -> Vec<String> {
+    let local = iana_time_zone::get_timezone().unwrap_or_default();
+    let mut zones: Vec<String> = Vec::new();
+    for line in unfold(text) {
+        let Some((_, params, _)) = split_line(&line) else {
+            continue;
+        };
+        for zone in params
+            .iter()
+            .filter_map(|param| param.strip_prefix("TZID="))
+        {
+            let zone = zone.trim_matches('"');
+            if !zone.eq_ignore_ascii_case(&local) && !zones.iter().any(|z| z == zone) {
+                zones.push(zone.to_string());
+            }
+        }
+    }
+    zones
+}
+
+//Lines longer than 75 characters are folded onto the next line, which starts with a space or tab
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+//NAME;PARAM=VALUE;...:VALUE, where a quoted parameter value can have a colon in it
+fn split_line(line: &str) -> Option<(String, Vec<String>, String)> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(idx, c)| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        (c == ':' && !quoted).then_some(idx)
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.to_uppercase();
+    let params = parts.map(str::to_uppercase).collect();
+    Some((name, params, value.to_string()))
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+//All day events come back as None, they're not meetings that need the timer to get out of the way
+fn parse_time(value: &str, params: &[String]) -> Option<NaiveDateTime> {
+    if params.iter().any(|param| param == "VALUE=DATE") {
+        return None;
+    }
+    let value = value.trim();
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        let time: DateTime<Local> = Utc.from_utc_datetime(&time).into();
+        return Some(time.naive_local());
+    }
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()
+}
+
+//Durations like PT1H30M, P1D or -PT15M (negatives make no sense for a meeting, so they're dropped)
+fn parse_duration(value: &str) -> Option<TimeDelta> {
+    let value = value.trim().strip_prefix('+').unwrap_or(value.trim());
+    let rest = value.strip_prefix('P')?;
+    let mut total = TimeDelta::zero();
+    let mut number = String::new();
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            'W' | 'D' | 'H' | 'M' | 'S' => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                total += match c {
+                    'W' => TimeDelta::weeks(n),
+                    'D' => TimeDelta::days(n),
+                    'H' => TimeDelta::hours(n),
+                    'M' => TimeDelta::minutes(n),
+                    _ => TimeDelta::seconds(n),
+                };
+            }
+            _ => return None,
+        }
+    }
+    Some(total)
+}
+
+fn parse_weekday(text: &str) -> Option<Weekday> {
+    //BYDAY can have a position in front, like 1MO, which only matters for monthly repeats
+    let day = text.trim_start_matches(|c: char| c.is_ascii_digit() || c == '-' || c == '+');
+    match day {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn parse_rule(value: &str) -> Option<Rule> {
+    let mut rule = Rule {
+        weekly: false,
+        interval: 1,
+        count: None,
+        until: None,
+        by_day: Vec::new(),
+    };
+    let mut repeats = false;
+    for part in value.split(';') {
+        let Some((key, val)) = part.split_once('=') else {
+            continue;
+        };
+        match key.to_uppercase().as_str() {
+            "FREQ" => {
+                repeats = matches!(val, "DAILY" | "WEEKLY");
+                rule.weekly = val == "WEEKLY";
+            }
+            "INTERVAL" => rule.interval = val.parse().unwrap_or(1).max(1),
+            "COUNT" => rule.count = val.parse().ok(),
+            "UNTIL" => {
+                rule.until = parse_time(val, &[]).or_else(|| {
+                    NaiveDate::parse_from_str(val, "%Y%m%d")
+                        .ok()
+                        .and_then(|date| date.and_hms_opt(23, 59, 59))
+                });
+            }
+            "BYDAY" => rule.by_day = val.split(',').filter_map(parse_weekday).collect(),
+            _ => {}
+        }
+    }
+    repeats.then_some(rule)
+}
+
+fn parse(text: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut event: Option<Event> = None;
+    let mut end: Option<NaiveDateTime> = None;
+    let mut skip = false;
+    //alarms and the like sit inside an event, their properties aren't the event's
+    let mut nested = 0;
+
+    for line in unfold(text) {
+        let Some((name, params, value)) = split_line(&line) else {
+            continue;
+        };
+        match (name.as_str(), value.to_uppercase().as_str()) {
+            ("BEGIN", "VEVENT") => {
+                event = Some(Event {
+                    uid: String::new(),
+                    summary: "Meeting".to_string(),
+                    start: NaiveDateTime::MIN,
+                    length: TimeDelta::zero(),
+                    rule: None,
+                    exdates: Vec::new(),
+                    recurrence_id: None,
+                });
+                end = None;
+                skip = false;
+                nested = 0;
+                continue;
+            }
+            ("END", "VEVENT") => {
+                if let Some(mut done) = event.take() {
+                    if let Some(end) = end {
+                        done.length = end - done.start;
+                    }
+                    if !skip && done.start != NaiveDateTime::MIN && done.length > TimeDelta::zero()
+                    {
+                        events.push(done);
+                    }
+                }
+                continue;
+            }
+            ("BEGIN", _) if event.is_some() => nested += 1,
+            ("END", _) if event.is_some() => nested -= 1,
+            _ => {}
+        }
+
+        let Some(event) = event.as_mut().filter(|_| nested == 0) else {
+            continue;
+        };
+        match name.as_str() {
+            "UID" => event.uid = value,
+            "SUMMARY" => event.summary = unescape(&value),
+            "DTSTART" => match parse_time(&value, &params) {
+                Some(start) => event.start = start,
+                None => skip = true,
+            },
+            "DTEND" => end = parse_time(&value, &params),
+            "DURATION" => event.length = parse_duration(&value).unwrap_or_default(),
+            "RRULE" => event.rule = parse_rule(&value),
+            "EXDATE" => event.exdates.extend(
+                value
+                    .split(',')
+                    .filter_map(|date| parse_time(date, &params)),
+            ),
+            "RECURRENCE-ID" => event.recurrence_id = parse_time(&value, &params),
+            //cancelled, or marked as free time
+            "STATUS" if value.eq_ignore_ascii_case("CANCELLED") => skip = true,
+            "TRANSP" if value.eq_ignore_ascii_case("TRANSPARENT") => skip = true,
+            _ => {}
+        }
+    }
+    events
+}
+
+//The starts of an event that overlap from..to
+fn occurrences(event: &Event, from: NaiveDateTime, to: NaiveDateTime) -> Vec<NaiveDateTime> {
+    let overlaps = |start: NaiveDateTime| start < to && start + event.length > from;
+    let Some(rule) = &event.rule else {
+        return if overlaps(event.start) {
+            vec![event.start]
+        } else {
+            Vec::new()
+        };
+    };
+
+    let first = event.start.date();
+    let week_of_first = first - TimeDelta::days(i64::from(first.weekday().num_days_from_monday()));
+    let by_day = if rule.by_day.is_empty() {
+        vec![first.weekday()]
+    } else {
+        rule.by_day.clone()
+    };
+
+    //Every day is walked from the first, since the count has to be kept from there
+    let mut starts = Vec::new();
+    let mut count = 0;
+    for date in first.iter_days().take_while(|date| *date <= to.date()) {
+        let matches = if rule.weekly {
+            by_day.contains(&date.weekday())
+                && ((date - week_of_first).num_days() / 7) % rule.interval == 0
+        } else {
+            (date - first).num_days() % rule.interval == 0
+        };
+        if !matches {
+            continue;
+        }
+
+        let start = date.and_time(event.start.time());
+        if rule.until.is_some_and(|until| start > until)
+            || rule.count.is_some_and(|max| count >= max)
+        {
+            break;
+        }
+        count += 1;
+        if overlaps(start) && !event.exdates.contains(&start) {
+            starts.push(start);
+        }
+    }
+    starts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn calendar(events: &str) -> String {
+        format!("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{events}END:VCALENDAR\r\n")
+    }
+
+    //the meetings in the fixture from the first to the last time, as start times with summaries
+    fn starts(events: &str, from: &str, to: &str) -> Vec<(String, String)> {
+        let events = parse(&calendar(events));
+        let events: Vec<&Event> = events.iter().collect();
+        meetings(&events, time(from), time(to))
+            .into_iter()
+            .map(|meeting| {
+                (
+                    meeting.start.format("%Y-%m-%d %H:%M").to_string(),
+                    meeting.summary,
+                )
+            })
+            .collect()
+    }
+
+    fn dates(events: &str, from: &str, to: &str) -> Vec<String> {
+        starts(events, from, to)
+            .into_iter()
+            .map(|(start, _)| start)
+            .collect()
+    }
+
+    #[test]
+    fn reads_a_plain_event() {
+        let events = parse(&calendar(
+            "BEGIN:VEVENT\r\nUID:1\r\nSUMMARY:Standup\\, daily\r\n\
+             DTSTART:20250602T093000\r\nDTEND:20250602T094500\r\nEND:VEVENT\r\n",
+        ));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Standup, daily");
+        assert_eq!(events[0].start, time("2025-06-02 09:30"));
+        assert_eq!(events[0].length, TimeDelta::minutes(15));
+    }
+
+    #[test]
+    fn unfolds_long_lines() {
+        let events = parse(&calendar(
+            "BEGIN:VEVENT\r\nSUMMARY:Quarterly plan\r\n ning review\r\n\
+             DTSTART:20250602T100000\r\nDURATION:PT1H\r\nEND:VEVENT\r\n",
+        ));
+        assert_eq!(events[0].summary, "Quarterly planning review");
+    }
+
+    #[test]
+    fn reads_durations() {
+        assert_eq!(parse_duration("PT1H30M"), Some(TimeDelta::minutes(90)));
+        assert_eq!(parse_duration("P1D"), Some(TimeDelta::days(1)));
+        assert_eq!(parse_duration("P1W"), Some(TimeDelta::weeks(1)));
+        assert_eq!(parse_duration("PT45S"), Some(TimeDelta::seconds(45)));
+        assert_eq!(parse_duration("-PT15M"), None);
+        assert_eq!(parse_duration("1H"), None);
+    }
+
+    #[test]
+    fn converts_utc_times() {
+        let events = parse(&calendar(
+            "BEGIN:VEVENT\r\nDTSTART:20250602T120000Z\r\nDURATION:PT30M\r\nEND:VEVENT\r\n",
+        ));
+        let utc = Utc.from_utc_datetime(&time("2025-06-02 12:00"));
+        assert_eq!(events[0].start, utc.with_timezone(&Local).naive_local());
+    }
+
+    #[test]
+    fn takes_tzid_times_as_local() {
+        let events = parse(&calendar(
+            "BEGIN:VEVENT\r\nDTSTART;TZID=\"Europe/Berlin\":20250602T120000\r\n\
+             DURATION:PT30M\r\nEND:VEVENT\r\n",
+        ));
+        assert_eq!(events[0].start, time("2025-06-02 12:00"));
+    }
+
+    #[test]
+    fn notices_other_time_zones() {
+        let zones = other_zones(&calendar(
+            "BEGIN:VEVENT\r\nDTSTART;TZID=Pacific/Chatham:20250602T120000\r\n\
+             DTEND;TZID=Pacific/Chatham:20250602T123000\r\nEND:VEVENT\r\n",
+        ));
+        if iana_time_zone::get_timezone().is_ok_and(|zone| zone != "Pacific/Chatham") {
+            assert_eq!(zones, ["PACIFIC/CHATHAM"]);
+        }
+    }
+
+    #[test]
+    fn skips_all_day_cancelled_and_free_events() {
+        let events = parse(&calendar(
+            "BEGIN:VEVENT\r\nSUMMARY:Holiday\r\nDTSTART;VALUE=DATE:20250602\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nSUMMARY:Off\r\nSTATUS:CANCELLED\r\n\
+             DTSTART:20250602T100000\r\nDURATION:PT1H\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nSUMMARY:Lunch\r\nTRANSP:TRANSPARENT\r\n\
+             DTSTART:20250602T120000\r\nDURATION:PT1H\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nSUMMARY:Kept\r\n\
+             DTSTART:20250602T140000\r\nDURATION:PT1H\r\nEND:VEVENT\r\n",
+        ));
+        let summaries: Vec<&str> = events.iter().map(|event| event.summary.as_str()).collect();
+        assert_eq!(summaries, ["Kept"]);
+    }
+
+    #[test]
+    fn ignores_alarm_properties() {
+        let events = parse(&calendar(
+            "BEGIN:VEVENT\r\nSUMMARY:Review\r\nDTSTART:20250602T100000\r\n\
+             BEGIN:VALARM\r\nSUMMARY:Reminder\r\nDURATION:PT5M\r\nEND:VALARM\r\n\
+             DTEND:20250602T110000\r\nEND:VEVENT\r\n",
+        ));
+        assert_eq!(events[0].summary, "Review");
+        assert_eq!(events[0].length, TimeDelta::hours(1));
+    }
+
+    #[test]
+    fn repeats_daily_with_an_interval_and_count() {
+        let event = "BEGIN:VEVENT\r\nDTSTART:20250602T090000\r\nDURATION:PT15M\r\n\
+                     RRULE:FREQ=DAILY;INTERVAL=2;COUNT=3\r\nEND:VEVENT\r\n";
+        assert_eq!(
+            dates(event, "2025-06-01 00:00", "2025-06-30 00:00"),
+            ["2025-06-02 09:00", "2025-06-04 09:00", "2025-06-06 09:00"]
+        );
+    }
+
+    #[test]
+    fn repeats_until_a_date() {
+        let event = "BEGIN:VEVENT\r\nDTSTART:20250602T090000\r\nDURATION:PT15M\r\n\
+                     RRULE:FREQ=DAILY;UNTIL=20250604\r\nEND:VEVENT\r\n";
+        assert_eq!(
+            dates(event, "2025-06-01 00:00", "2025-06-30 00:00"),
+            ["2025-06-02 09:00", "2025-06-03 09:00", "2025-06-04 09:00"]
+        );
+    }
+
+    #[test]
+    fn repeats_weekly_on_the_given_days() {
+        //2025-06-02 is a Monday
+        let event = "BEGIN:VEVENT\r\nDTSTART:20250602T090000\r\nDURATION:PT15M\r\n\
+                     RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH\r\nEND:VEVENT\r\n";
+        assert_eq!(
+            dates(event, "2025-06-01 00:00", "2025-06-20 00:00"),
+            [
+                "2025-06-02 09:00",
+                "2025-06-05 09:00",
+                "2025-06-16 09:00",
+                "2025-06-19 09:00"
+            ]
+        );
+    }
+
+    #[test]
+    fn repeats_weekly_on_the_first_day_without_byday() {
+        let event = "BEGIN:VEVENT\r\nDTSTART:20250604T090000\r\nDURATION:PT15M\r\n\
+                     RRULE:FREQ=WEEKLY\r\nEND:VEVENT\r\n";
+        assert_eq!(
+            dates(event, "2025-06-01 00:00", "2025-06-20 00:00"),
+            ["2025-06-04 09:00", "2025-06-11 09:00", "2025-06-18 09:00"]
+        );
+    }
+
+    #[test]
+    fn leaves_out_excluded_dates() {
+        let event = "BEGIN:VEVENT\r\nDTSTART:20250602T090000\r\nDURATION:PT15M\r\n\
+                     RRULE:FREQ=DAILY;COUNT=4\r\nEXDATE:20250603T090000,20250605T090000\r\n\
+                     END:VEVENT\r\n";
+        assert_eq!(
+            dates(event, "2025-06-01 00:00", "2025-06-30 00:00"),
+            ["2025-06-02 09:00", "2025-06-04 09:00"]
+        );
+    }
+
+    #[test]
+    fn moved_occurrences_replace_the_original() {
+        let events = "BEGIN:VEVENT\r\nUID:sync\r\nSUMMARY:Sync\r\nDTSTART:20250602T090000\r\n\
+                      DURATION:PT30M\r\nRRULE:FREQ=DAILY;COUNT=3\r\nEND:VEVENT\r\n\
+                      BEGIN:VEVENT\r\nUID:sync\r\nSUMMARY:Sync (moved)\r\n\
+                      RECURRENCE-ID:20250603T090000\r\nDTSTART:20250603T150000\r\n\
+                      DURATION:PT30M\r\nEND:VEVENT\r\n";
+        assert_eq!(
+            starts(events, "2025-06-01 00:00", "2025-06-30 00:00"),
+            [
+                ("2025-06-02 09:00".to_string(), "Sync".to_string()),
+                ("2025-06-03 15:00".to_string(), "Sync (moved)".to_string()),
+                ("2025-06-04 09:00".to_string(), "Sync".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_overlapping_meetings_are_kept() {
+        let event = "BEGIN:VEVENT\r\nDTSTART:20250602T090000\r\nDURATION:PT1H\r\n\
+                     RRULE:FREQ=DAILY\r\nEND:VEVENT\r\n";
+        assert_eq!(
+            dates(event, "2025-06-03 09:30", "2025-06-04 09:00"),
+            ["2025-06-03 09:00"]
+        );
+    }
+}
@@ -0,0 +1,419 @@
+use crate::settings::{self, JsonPhase};
+use chrono::{DateTime, Local, NaiveTime, TimeDelta};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+//Each phase that ends is appended to the file as a line of JSON, so nothing already recorded ever
+//needs rewriting and a crash can only cost the line being written at the time.
+const HISTORY_FILE: &str = "history.jsonl";
+
+//Most records are a phase of the timer, the others are time set aside from one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SessionKind {
+    #[default]
+    Phase,
+    //time away from the computer during focus, and whether it was left counted as focus or not
+    IdleKept,
+    IdleDiscarded,
+    //the screen was locked or the computer was asleep
+    Away,
+    //a meeting from one of the calendars, named in the phase
+    Meeting,
+    //The rating and note for a focus round, written once the break screen is done with. It's
+    //matched back up with the round by when that ended, and only ever seen merged into it.
+    Reflection,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    #[serde(default)]
+    pub kind: SessionKind,
+    //seconds since the Unix epoch when the phase ended
+    pub ended: u64,
+    pub phase: String,
+    pub focus: bool,
+    //all in seconds. Elapsed is the time the timer actually ran for, so it leaves out any pauses,
+    //is short of planned if the phase was skipped and includes the overtime
+    pub planned: i64,
+    pub elapsed: i64,
+    pub overtime: i64,
+    //ended early because it was skipped, rather than by a meeting or the screen locking
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+    //from the reflection after a focus round, a 1 to 5 rating and a one line note
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+}
+
+impl Session {
+    //Shortened is how many seconds were cut from the phase to finish before a meeting. What's left
+    //is what was planned, so running that down still counts as a round.
+    #[must_use]
+    pub fn ended_now(
+        phase: &JsonPhase,
+        shortened: i64,
+        elapsed: i64,
+        overtime: i64,
+        skipped: bool,
+    ) -> Self {
+        Self {
+            kind: SessionKind::Phase,
+            ended: now(),
+            phase: phase.name.clone(),
+            focus: phase.focus,
+            planned: i64::from(phase.seconds) - shortened,
+            elapsed,
+            overtime,
+            skipped,
+            rating: None,
+            note: String::new(),
+        }
+    }
+
+    //Kept idle time is already part of the focus phase's elapsed time, so this doesn't count
+    //as focus itself either way
+    #[must_use]
+    pub fn idle(seconds: i64, kept: bool) -> Self {
+        Self {
+            kind: if kept {
+                SessionKind::IdleKept
+            } else {
+                SessionKind::IdleDiscarded
+            },
+            ended: now(),
+            phase: "Idle".to_string(),
+            focus: false,
+            planned: 0,
+            elapsed: seconds,
+            overtime: 0,
+            skipped: false,
+            rating: None,
+            note: String::new(),
+        }
+    }
+
+    //Like idle time, this is only a note of when the user was gone. Whatever the timer did in the
+    //meantime is recorded with the phases.
+    #[must_use]
+    pub fn away(seconds: i64) -> Self {
+        Self {
+            kind: SessionKind::Away,
+            ended: now(),
+            phase: "Away".to_string(),
+            focus: false,
+            planned: 0,
+            elapsed: seconds,
+            overtime: 0,
+            skipped: false,
+            rating: None,
+            note: String::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn reflection(focus_ended: u64, rating: Option<u8>, note: &str) -> Self {
+        Self {
+            kind: SessionKind::Reflection,
+            ended: focus_ended,
+            phase: String::new(),
+            focus: false,
+            planned: 0,
+            elapsed: 0,
+            overtime: 0,
+            skipped: false,
+            rating,
+            note: note.to_string(),
+        }
+    }
+
+    //Only the breaks skipped on purpose, the ones ended by a meeting or by the screen locking
+    //don't use up the day's skips
+    #[must_use]
+    pub fn skipped_break(&self) -> bool {
+        !self.focus && self.skipped
+    }
+
+    //Planned is the meeting's length from the calendar, elapsed how much of it Tomotroid was
+    //running for
+    #[must_use]
+    pub fn meeting(summary: &str, planned: i64, elapsed: i64) -> Self {
+        Self {
+            kind: SessionKind::Meeting,
+            ended: now(),
+            phase: summary.to_string(),
+            focus: false,
+            planned,
+            elapsed,
+            overtime: 0,
+            skipped: false,
+            rating: None,
+            note: String::new(),
+        }
+    }
+
+    //A focus phase that ran all the way down rather than being skipped. Going back to focus for a
+    //snooze is recorded against the full focus time, so it doesn't count as another round.
+    #[must_use]
+    pub fn completed_round(&self) -> bool {
+        self.focus && self.elapsed >= self.planned
+    }
+}
+
+//Everything recorded so far. A line that can't be read, like one cut short by a crash, is skipped
+//rather than losing the rest of the history over it. Reflections come back as part of the focus
+//rounds they're about.
+#[must_use]
+pub fn load() -> Vec<Session> {
+    let Some(file) =
+        settings::data_dir().and_then(|dir| std::fs::File::open(dir.join(HISTORY_FILE)).ok())
+    else {
+        return Vec::new();
+    };
+    read_sessions(BufReader::new(file))
+}
+
+fn read_sessions(reader: impl BufRead) -> Vec<Session> {
+    let (reflections, mut sessions): (Vec<Session>, Vec<Session>) = reader
+        .lines()
+        .map_while(Result::ok)
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str::<Session>(&line) {
+            Ok(session) => Some(session),
+            Err(e) => {
+                warn!("Skipping a session in the history that can't be read: {e}");
+                None
+            }
+        })
+        .partition(|session| session.kind == SessionKind::Reflection);
+
+    for reflection in reflections {
+        if let Some(session) = sessions.iter_mut().rev().find(|session| {
+            session.kind == SessionKind::Phase && session.focus && session.ended == reflection.ended
+        }) {
+            session.rating = reflection.rating.or(session.rating);
+            if !reflection.note.is_empty() {
+                session.note = reflection.note;
+            }
+        }
+    }
+    sessions
+}
+
+//The sessions that ended since the day started. Late nights count towards the day before until the
+//day_start hour, so with it set to 4 a round finished at 1am still counts as yesterday.
+#[must_use]
+pub fn today(day_start: u32) -> Vec<Session> {
+    let now = Local::now().naive_local();
+    let mut start = now
+        .date()
+        .and_time(NaiveTime::from_hms_opt(day_start, 0, 0).unwrap_or(NaiveTime::MIN));
+    if now < start {
+        start -= TimeDelta::days(1);
+    }
+    let start = start
+        .and_local_timezone(Local)
+        .earliest()
+        .map_or(0, |start| u64::try_from(start.timestamp()).unwrap_or(0));
+
+    load()
+        .into_iter()
+        .filter(|session| session.ended >= start)
+        .collect()
+}
+
+//What the daily goal is measured against
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DayTotals {
+    pub rounds: usize,
+    //seconds of focus, counting overtime and any part of a round that was skipped
+    pub focus: i64,
+    //breaks ended before their time was up
+    pub skipped_breaks: usize,
+    //for the average of the focus ratings
+    pub rating_sum: u32,
+    pub rated: u32,
+}
+
+impl DayTotals {
+    #[must_use]
+    pub fn average_rating(&self) -> Option<f32> {
+        #[allow(clippy::cast_precision_loss)] //they're only small numbers
+        (self.rated > 0).then(|| self.rating_sum as f32 / self.rated as f32)
+    }
+}
+
+#[must_use]
+pub fn day_totals(day_start: u32) -> DayTotals {
+    today(day_start)
+        .iter()
+        .fold(DayTotals::default(), |totals, session| DayTotals {
+            rounds: totals.rounds + usize::from(session.completed_round()),
+            focus: totals.focus + if session.focus { session.elapsed } else { 0 },
+            skipped_breaks: totals.skipped_breaks
+                + usize::from(session.kind == SessionKind::Phase && session.skipped_break()),
+            rating_sum: totals.rating_sum + u32::from(session.rating.unwrap_or(0)),
+            rated: totals.rated + u32::from(session.rating.is_some()),
+        })
+}
+
+//Quotes a CSV field if it needs it
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+//The whole history as CSV for a spreadsheet, with the times in local time
+pub fn export_csv(out: &mut impl Write) -> std::io::Result<()> {
+    write_csv(load(), out)
+}
+
+fn write_csv(sessions: Vec<Session>, out: &mut impl Write) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "ended,kind,phase,focus,planned,elapsed,overtime,rating,note"
+    )?;
+    for session in sessions {
+        let ended = i64::try_from(session.ended)
+            .ok()
+            .and_then(|ended| DateTime::from_timestamp(ended, 0))
+            .map(|ended| {
+                ended
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let kind = serde_json::to_value(session.kind)
+            .ok()
+            .and_then(|kind| kind.as_str().map(str::to_string))
+            .unwrap_or_default();
+        writeln!(
+            out,
+            "{ended},{kind},{},{},{},{},{},{},{}",
+            csv_field(&session.phase),
+            session.focus,
+            session.planned,
+            session.elapsed,
+            session.overtime,
+            session
+                .rating
+                .map(|rating| rating.to_string())
+                .unwrap_or_default(),
+            csv_field(&session.note)
+        )?;
+    }
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+//Losing a record isn't worth interrupting the timer over, so failures are only logged
+pub fn record(session: &Session) {
+    let Some(dir) = settings::data_dir() else {
+        error!("Unable to find the data directory to record the session in");
+        return;
+    };
+    let line = match serde_json::to_string(session) {
+        Ok(line) => line,
+        Err(e) => {
+            error!("Unable to serialize the session: {e}");
+            return;
+        }
+    };
+
+    let written = std::fs::create_dir_all(dir).and_then(|()| {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(HISTORY_FILE))?;
+        writeln!(file, "{line}")
+    });
+    if let Err(e) = written {
+        error!("Unable to record the session: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{JsonPhaseColor, JsonPhaseSound};
+
+    fn phase(focus: bool, seconds: i32) -> JsonPhase {
+        JsonPhase {
+            name: if focus { "Focus" } else { "Break" }.to_string(),
+            seconds,
+            color: JsonPhaseColor::default(),
+            sound: JsonPhaseSound::default(),
+            focus,
+        }
+    }
+
+    #[test]
+    fn shortened_focus_still_counts_as_a_round() {
+        let session = Session::ended_now(&phase(true, 25 * 60), 5 * 60, 20 * 60, 0, false);
+        assert_eq!(session.planned, 20 * 60);
+        assert!(session.completed_round());
+    }
+
+    #[test]
+    fn focus_ended_early_is_not_a_round() {
+        let session = Session::ended_now(&phase(true, 25 * 60), 5 * 60, 15 * 60, 0, false);
+        assert!(!session.completed_round());
+    }
+
+    #[test]
+    fn only_skipped_breaks_count_as_skips() {
+        let skipped = Session::ended_now(&phase(false, 5 * 60), 0, 60, 0, true);
+        assert!(skipped.skipped_break());
+        let ended_for_meeting = Session::ended_now(&phase(false, 5 * 60), 0, 60, 0, false);
+        assert!(!ended_for_meeting.skipped_break());
+    }
+
+    #[test]
+    fn reflections_are_merged_into_the_round_they_are_about() {
+        let lines = [
+            r#"{"ended":100,"phase":"Focus","focus":true,"planned":1500,"elapsed":1500,"overtime":0}"#,
+            r#"{"ended":400,"phase":"Break","focus":false,"planned":300,"elapsed":300,"overtime":0}"#,
+            r#"{"ended":1900,"phase":"Focus","focus":true,"planned":1500,"elapsed":1500,"overtime":0}"#,
+            "not a session",
+            r#"{"kind":"reflection","ended":100,"phase":"Focus","focus":true,"planned":0,"elapsed":0,"overtime":0,"rating":4,"note":"went well"}"#,
+            r#"{"kind":"reflection","ended":999,"phase":"Focus","focus":true,"planned":0,"elapsed":0,"overtime":0,"rating":1}"#,
+        ];
+        let sessions = read_sessions(lines.join("\n").as_bytes());
+
+        assert_eq!(sessions.len(), 3);
+        assert!(sessions.iter().all(|s| s.kind == SessionKind::Phase));
+        assert_eq!(sessions[0].rating, Some(4));
+        assert_eq!(sessions[0].note, "went well");
+        assert_eq!(sessions[1].rating, None);
+        assert_eq!(sessions[2].rating, None);
+    }
+
+    #[test]
+    fn notes_are_quoted_in_csv() {
+        let mut session = Session::ended_now(&phase(true, 25 * 60), 0, 25 * 60, 0, false);
+        session.note = "fixed it, mostly\nsaid \"done\"".to_string();
+        let mut out = Vec::new();
+        write_csv(vec![session], &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+
+        assert!(csv.ends_with(",\"fixed it, mostly\nsaid \"\"done\"\"\"\n"));
+        assert_eq!(csv_field("plain"), "plain");
+    }
+}
@@ -0,0 +1,205 @@
+#[cfg_attr(target_os = "macos", allow(unused_imports))] //there's nothing to detect with yet
+use log::{info, warn};
+use std::time::Duration;
+
+//How often the idle time is checked. Idle thresholds are in minutes, so there's no need to be quick.
+pub const POLL: Duration = Duration::from_secs(5);
+
+//Asks the system how long it's been since the last keyboard or mouse input. Each platform (and on
+//Linux each desktop) has its own way of answering, so whichever works is picked once up front.
+pub struct IdleMonitor {
+    source: Source,
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+enum Source {
+    //XWayland only sees input going to X11 windows, so this is only used in an X11 session
+    X11 {
+        conn: Box<x11rb::rust_connection::RustConnection>,
+        root: u32,
+    },
+    //GNOME doesn't implement GetSessionIdleTime, but has its own idle monitor
+    Mutter(zbus::blocking::Connection),
+    //KDE and most of the others
+    ScreenSaver(zbus::blocking::Connection),
+    //The session's idle hint from logind. It's only set once the desktop decides the session is
+    //idle, so it's the least precise and only used when nothing else answers.
+    Logind(zbus::blocking::Connection),
+}
+
+#[cfg(windows)]
+enum Source {
+    LastInput,
+}
+
+#[cfg(target_os = "macos")]
+enum Source {}
+
+impl IdleMonitor {
+    //None if there's no way of telling on this system
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn new() -> Option<Self> {
+        let source = if crate::settings::is_wayland() {
+            None
+        } else {
+            x11_source()
+        }
+        .or_else(|| {
+            let session = zbus::blocking::Connection::session().ok()?;
+            [
+                Source::Mutter(session.clone()),
+                Source::ScreenSaver(session),
+            ]
+            .into_iter()
+            .find(|source| source.idle_time().is_some())
+        })
+        .or_else(|| {
+            let source = Source::Logind(zbus::blocking::Connection::system().ok()?);
+            source.idle_time().is_some().then_some(source)
+        });
+
+        if source.is_none() {
+            warn!("Unable to find a way to detect when the computer is idle");
+        }
+        source.map(|source| Self { source })
+    }
+
+    #[cfg(windows)]
+    pub fn new() -> Option<Self> {
+        let source = Source::LastInput;
+        if source.idle_time().is_none() {
+            warn!("Unable to read the time of the last input");
+            return None;
+        }
+        info!("Detecting idle through the time of the last input");
+        Some(Self { source })
+    }
+
+    #[cfg(target_os = "macos")]
+    pub fn new() -> Option<Self> {
+        warn!("Idle detection isn't supported on macOS yet");
+        None
+    }
+
+    pub fn idle_time(&self) -> Option<Duration> {
+        self.source.idle_time()
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn x11_source() -> Option<Source> {
+    use x11rb::{connection::Connection, protocol::screensaver::ConnectionExt};
+
+    let (conn, screen) = x11rb::connect(None).ok()?;
+    let root = conn.setup().roots.get(screen)?.root;
+    //make sure the server has the screensaver extension before relying on it
+    conn.screensaver_query_version(1, 1).ok()?.reply().ok()?;
+    info!("Detecting idle through the X11 screensaver extension");
+    Some(Source::X11 {
+        conn: Box::new(conn),
+        root,
+    })
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl Source {
+    fn idle_time(&self) -> Option<Duration> {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        use x11rb::protocol::screensaver::ConnectionExt;
+        use zbus::zvariant::OwnedValue;
+
+        match self {
+            Source::X11 { conn, root } => {
+                let info = conn.screensaver_query_info(*root).ok()?.reply().ok()?;
+                Some(Duration::from_millis(u64::from(info.ms_since_user_input)))
+            }
+            Source::Mutter(conn) => {
+                let reply = conn
+                    .call_method(
+                        Some("org.gnome.Mutter.IdleMonitor"),
+                        "/org/gnome/Mutter/IdleMonitor/Core",
+                        Some("org.gnome.Mutter.IdleMonitor"),
+                        "GetIdletime",
+                        &(),
+                    )
+                    .ok()?;
+                Some(Duration::from_millis(
+                    reply.body().deserialize::<u64>().ok()?,
+                ))
+            }
+            Source::ScreenSaver(conn) => {
+                let reply = conn
+                    .call_method(
+                        Some("org.freedesktop.ScreenSaver"),
+                        "/org/freedesktop/ScreenSaver",
+                        Some("org.freedesktop.ScreenSaver"),
+                        "GetSessionIdleTime",
+                        &(),
+                    )
+                    .ok()?;
+                Some(Duration::from_millis(u64::from(
+                    reply.body().deserialize::<u32>().ok()?,
+                )))
+            }
+            Source::Logind(conn) => {
+                let property = |name: &str| -> Option<OwnedValue> {
+                    let reply = conn
+                        .call_method(
+                            Some("org.freedesktop.login1"),
+                            "/org/freedesktop/login1/session/auto",
+                            Some("org.freedesktop.DBus.Properties"),
+                            "Get",
+                            &("org.freedesktop.login1.Session", name),
+                        )
+                        .ok()?;
+                    reply.body().deserialize::<OwnedValue>().ok()
+                };
+
+                if !bool::try_from(property("IdleHint")?).ok()? {
+                    return Some(Duration::ZERO);
+                }
+                //microseconds since the epoch when the session went idle
+                let since = u64::try_from(property("IdleSinceHint")?).ok()?;
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+                Some(now.saturating_sub(Duration::from_micros(since)))
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Source {
+    #[allow(clippy::cast_possible_truncation)] //the struct size is tiny
+    fn idle_time(&self) -> Option<Duration> {
+        use windows_sys::Win32::{
+            System::SystemInformation::GetTickCount,
+            UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO},
+        };
+
+        match self {
+            Source::LastInput => {
+                let mut info = LASTINPUTINFO {
+                    cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+                    dwTime: 0,
+                };
+                // SAFETY: info is a properly sized LASTINPUTINFO that lives for the whole call
+                if unsafe { GetLastInputInfo(&mut info) } == 0 {
+                    return None;
+                }
+                // SAFETY: no arguments, it just reads the tick count
+                let now = unsafe { GetTickCount() };
+                //both are milliseconds since boot and wrap after 49 days, so wrap the difference too
+                Some(Duration::from_millis(u64::from(
+                    now.wrapping_sub(info.dwTime),
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Source {
+    fn idle_time(&self) -> Option<Duration> {
+        match *self {}
+    }
+}
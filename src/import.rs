@@ -0,0 +1,161 @@
+use crate::settings::{self, JsonThemeTemp};
+use crate::JsonTheme;
+use directories::BaseDirs;
+use log::{error, info, warn};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+//Pomotroid is an Electron app, so everything it keeps is in its userData folder. That's the same
+//place directories puts the config dir on each OS (~/.config, %APPDATA% and ~/Library/Application Support)
+//It doesn't save any timer state between runs, the preferences and custom themes are all there is to bring over.
+const POMOTROID_DIR: &str = "pomotroid";
+const POMOTROID_PREFS: &str = "user-preferences.json";
+
+fn pomotroid_dirs() -> Vec<PathBuf> {
+    let Some(base) = BaseDirs::new() else {
+        return Vec::new();
+    };
+
+    #[allow(unused_mut)]
+    let mut dirs = vec![base.config_dir().join(POMOTROID_DIR)];
+    //the Flatpak and Snap packages keep their config inside their sandboxes
+    #[cfg(target_os = "linux")]
+    dirs.extend([
+        base.home_dir()
+            .join(".var/app/com.splode.pomotroid/config")
+            .join(POMOTROID_DIR),
+        base.home_dir()
+            .join("snap/pomotroid/current/.config")
+            .join(POMOTROID_DIR),
+    ]);
+    dirs
+}
+
+//Only runs before Tomotroid has saved any settings of its own, so it never overwrites anything.
+//Returns a message for the user saying what was imported and what couldn't be.
+pub fn first_run_import() -> Option<String> {
+    if settings::settings_exist() {
+        return None;
+    }
+    let theme_dir = settings::theme_dir()?;
+    let pomo_dir = pomotroid_dirs()
+        .into_iter()
+        .find(|dir| dir.join(POMOTROID_PREFS).is_file())?;
+    info!(
+        "Importing the Pomotroid settings from {}",
+        pomo_dir.display()
+    );
+
+    let mut not_imported = Vec::new();
+    let themes = import_themes(&pomo_dir.join("themes"), &theme_dir, &mut not_imported);
+
+    let prefs = std::fs::read_to_string(pomo_dir.join(POMOTROID_PREFS))
+        .map_err(|e| e.to_string())
+        .and_then(|prefs| serde_json::from_str::<Value>(&prefs).map_err(|e| e.to_string()));
+    let mut settings = match prefs {
+        Ok(prefs) => {
+            let (settings, skipped) = settings::import_settings(&prefs);
+            not_imported.extend(skipped.iter().map(|pointer| {
+                format!(
+                    "the {} setting",
+                    pointer.trim_start_matches('/').replace('/', ".")
+                )
+            }));
+            settings
+        }
+        Err(e) => {
+            error!("Unable to read the Pomotroid preferences: {e}");
+            return Some(format!(
+                "Found Pomotroid, but its preferences couldn't be read: {e}"
+            ));
+        }
+    };
+
+    //Pomotroid's built in themes aren't in its themes folder, so the one picked might not be here
+    if !settings::load_themes()
+        .iter()
+        .any(|theme| theme.name == settings.theme)
+    {
+        not_imported.push(format!("the {} theme", settings.theme));
+        settings.theme = settings::default_settings().theme;
+    }
+
+    if let Err(e) = settings::save_settings(&settings) {
+        return Some(format!(
+            "Found your Pomotroid settings, but they couldn't be saved: {e}"
+        ));
+    }
+    Some(import_notice(themes, &not_imported))
+}
+
+//Copies over any themes Tomotroid doesn't already have a file for, checking each one can be loaded
+//first. Returns how many were copied.
+fn import_themes(from: &Path, to: &Path, not_imported: &mut Vec<String>) -> usize {
+    let Ok(entries) = std::fs::read_dir(from) else {
+        //no custom themes
+        return 0;
+    };
+    if let Err(e) = std::fs::create_dir_all(to) {
+        error!("Unable to create the themes folder: {e}");
+        not_imported.push("any of the custom themes".to_string());
+        return 0;
+    }
+
+    let mut copied = 0;
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        if !path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+        {
+            continue;
+        }
+        let Some(file_name) = path.file_name() else {
+            continue;
+        };
+        let dest = to.join(file_name);
+        if dest.exists() {
+            continue;
+        }
+
+        let theme = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|theme| {
+                serde_json::from_str::<JsonThemeTemp>(&theme).map_err(|e| e.to_string())
+            });
+        match theme {
+            Ok(theme) => {
+                let theme: JsonTheme = theme.into();
+                match std::fs::copy(&path, &dest) {
+                    Ok(_) => {
+                        info!("Imported the {} theme", theme.name);
+                        copied += 1;
+                    }
+                    Err(e) => {
+                        error!("Unable to copy the theme {}: {e}", path.display());
+                        not_imported.push(format!("the {} theme", theme.name));
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("Skipping the theme {}: {e}", path.display());
+                not_imported.push(format!("the theme in {}", file_name.to_string_lossy()));
+            }
+        }
+    }
+    copied
+}
+
+fn import_notice(themes: usize, not_imported: &[String]) -> String {
+    let imported = match themes {
+        0 => "Imported your Pomotroid settings.".to_string(),
+        1 => "Imported your Pomotroid settings and 1 theme.".to_string(),
+        n => format!("Imported your Pomotroid settings and {n} themes."),
+    };
+    if not_imported.is_empty() {
+        return imported;
+    }
+
+    let not_imported = not_imported.join(", ");
+    warn!("Couldn't import from Pomotroid: {not_imported}");
+    format!("{imported} Couldn't bring over {not_imported}.")
+}
@@ -0,0 +1,270 @@
+use log::warn;
+use std::cell::RefCell;
+use std::time::SystemTime;
+
+//What took the user away. A lock screen usually comes up before the computer goes to sleep, and
+//is still up when it wakes, so both are tracked and they're only back once neither is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    Sleep,
+    ScreenLock,
+}
+
+//What the timer was doing when the first lock or sleep came in
+#[derive(Debug, Clone)]
+pub struct Away {
+    pub running: bool,
+    pub since: SystemTime,
+    reasons: Vec<Reason>,
+    //how long of it the computer was asleep for, and since when if it still is
+    slept: i64,
+    sleeping: Option<SystemTime>,
+}
+
+fn seconds_since(since: SystemTime) -> i64 {
+    since
+        .elapsed()
+        .map_or(0, |away| i64::try_from(away.as_secs()).unwrap_or(i64::MAX))
+}
+
+impl Away {
+    //Wall clock time, since the timer doesn't tick while the computer sleeps
+    #[must_use]
+    pub fn seconds(&self) -> i64 {
+        seconds_since(self.since)
+    }
+
+    //the part of it the timer missed, a locked screen alone doesn't stop it ticking
+    #[must_use]
+    pub fn slept(&self) -> i64 {
+        self.slept
+    }
+
+    fn add(&mut self, reason: Reason) {
+        if !self.reasons.contains(&reason) {
+            self.reasons.push(reason);
+        }
+        if reason == Reason::Sleep && self.sleeping.is_none() {
+            self.sleeping = Some(SystemTime::now());
+        }
+    }
+
+    fn remove(&mut self, reason: Reason) {
+        self.reasons.retain(|r| *r != reason);
+        if reason == Reason::Sleep {
+            if let Some(since) = self.sleeping.take() {
+                self.slept = self.slept.saturating_add(seconds_since(since));
+            }
+        }
+    }
+}
+
+//Only ever touched from the event loop
+thread_local! {
+    static AWAY: RefCell<Option<Away>> = const { RefCell::new(None) };
+}
+
+//true if this is the start of the time away rather than another reason on top of it
+pub fn locked(reason: Reason, running: bool) -> bool {
+    AWAY.with_borrow_mut(|away| {
+        if let Some(away) = away {
+            away.add(reason);
+            return false;
+        }
+        let mut first = Away {
+            running,
+            since: SystemTime::now(),
+            reasons: Vec::new(),
+            slept: 0,
+            sleeping: None,
+        };
+        first.add(reason);
+        *away = Some(first);
+        true
+    })
+}
+
+//the time away, once the last reason for it has cleared
+pub fn unlocked(reason: Reason) -> Option<Away> {
+    AWAY.with_borrow_mut(|away| {
+        away.as_mut()?.remove(reason);
+        if away.as_ref()?.reasons.is_empty() {
+            away.take()
+        } else {
+            None
+        }
+    })
+}
+
+//Listens on D-Bus for logind's PrepareForSleep on the system bus and the screensaver's
+//ActiveChanged on the session bus. GNOME sends the latter from org.gnome.ScreenSaver and most
+//others from org.freedesktop.ScreenSaver, so both are listened for. Each one gets its own thread,
+//which ends when on_change returns false.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn watch(on_change: impl Fn(Reason, bool) -> bool + Send + Clone + 'static) {
+    let watchers = [
+        (
+            true,
+            "org.freedesktop.login1.Manager",
+            "PrepareForSleep",
+            Reason::Sleep,
+        ),
+        (
+            false,
+            "org.freedesktop.ScreenSaver",
+            "ActiveChanged",
+            Reason::ScreenLock,
+        ),
+        (
+            false,
+            "org.gnome.ScreenSaver",
+            "ActiveChanged",
+            Reason::ScreenLock,
+        ),
+    ];
+    for (system, interface, member, reason) in watchers {
+        let on_change = on_change.clone();
+        std::thread::spawn(move || {
+            let conn = if system {
+                zbus::blocking::Connection::system()
+            } else {
+                zbus::blocking::Connection::session()
+            };
+            if let Err(e) = conn
+                .and_then(|conn| subscribe(&conn, interface, member))
+                .and_then(|messages| listen(messages, interface, member, reason, on_change))
+            {
+                warn!("Unable to listen for {interface} {member}: {e}");
+            }
+        });
+    }
+}
+
+//Signals sent before this returns are missed, once it has they queue up until they're listened for
+#[cfg(all(unix, not(target_os = "macos")))]
+fn subscribe(
+    conn: &zbus::blocking::Connection,
+    interface: &'static str,
+    member: &'static str,
+) -> zbus::Result<zbus::blocking::MessageIterator> {
+    let rule = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .interface(interface)?
+        .member(member)?
+        .build();
+    zbus::blocking::MessageIterator::for_match_rule(rule, conn, None)
+}
+
+//Both signals carry a single bool for whether it's started or ended. Anything else sent under the
+//same name is logged and passed over rather than ending the watch.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn listen(
+    messages: zbus::blocking::MessageIterator,
+    interface: &'static str,
+    member: &'static str,
+    reason: Reason,
+    on_change: impl Fn(Reason, bool) -> bool,
+) -> zbus::Result<()> {
+    for msg in messages {
+        let state: bool = match msg?.body().deserialize() {
+            Ok(state) => state,
+            Err(e) => {
+                warn!("Ignoring a {interface} {member} that can't be read: {e}");
+                continue;
+            }
+        };
+        if !on_change(reason, state) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn watch(_on_change: impl Fn(Reason, bool) -> bool + Send + Clone + 'static) {
+    warn!("Watching for the screen locking isn't supported on this platform yet");
+}
+
+#[cfg(all(test, unix, not(target_os = "macos")))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+    use std::sync::mpsc;
+    use zbus::blocking::connection::Builder;
+
+    //A stand-in for the screensaver, talking straight to the watcher without a bus in between.
+    //Newer versions of zbus would rather have an async-io stream, which 5.5 doesn't take yet.
+    #[test]
+    #[allow(deprecated)]
+    fn screen_lock_survives_unrelated_and_unreadable_signals() {
+        let (service, watcher) = UnixStream::pair().unwrap();
+        let service = std::thread::spawn(move || {
+            Builder::unix_stream(service)
+                .server(zbus::Guid::generate())
+                .unwrap()
+                .p2p()
+                .build()
+                .unwrap()
+        });
+        let watcher = Builder::unix_stream(watcher).p2p().build().unwrap();
+        let service = service.join().unwrap();
+
+        let (sender, changes) = mpsc::channel();
+        let (subscribed, ready) = mpsc::channel();
+        let listener = std::thread::spawn(move || {
+            let interface = "org.freedesktop.ScreenSaver";
+            let messages = subscribe(&watcher, interface, "ActiveChanged")?;
+            subscribed.send(()).unwrap();
+            listen(
+                messages,
+                interface,
+                "ActiveChanged",
+                Reason::ScreenLock,
+                move |reason, active| {
+                    sender.send((reason, active)).unwrap();
+                    active
+                },
+            )
+        });
+        //nothing is sent until the watcher's match rule is in place
+        ready.recv().unwrap();
+
+        let path = "/org/freedesktop/ScreenSaver";
+        service
+            .emit_signal(
+                None::<()>,
+                path,
+                "org.example.Other",
+                "ActiveChanged",
+                &(false,),
+            )
+            .unwrap();
+        service
+            .emit_signal(
+                None::<()>,
+                path,
+                "org.freedesktop.ScreenSaver",
+                "ActiveChanged",
+                &("locked",),
+            )
+            .unwrap();
+        for active in [true, false] {
+            service
+                .emit_signal(
+                    None::<()>,
+                    path,
+                    "org.freedesktop.ScreenSaver",
+                    "ActiveChanged",
+                    &(active,),
+                )
+                .unwrap();
+        }
+
+        assert!(listener.join().unwrap().is_ok());
+        let changes: Vec<_> = changes.iter().collect();
+        assert_eq!(
+            changes,
+            vec![(Reason::ScreenLock, true), (Reason::ScreenLock, false)]
+        );
+    }
+}
@@ -5,26 +5,43 @@
 )]
 #![windows_subsystem = "windows"]
 
+mod activities;
+mod calendar;
+mod history;
+mod idle;
+mod import;
+mod lock;
+mod overlay;
+mod schedule;
 mod settings;
 mod setup;
 
-use crate::setup::TrayMsg;
+use crate::setup::{TrayMsg, TrayStatus};
 
 use anyhow::Result;
-use global_hotkey::GlobalHotKeyEvent;
+use chrono::{Local, TimeDelta};
 use global_hotkey::{
-    hotkey::{Code, HotKey},
+    hotkey::{Code, HotKey, Modifiers},
     GlobalHotKeyManager,
 };
+use global_hotkey::{GlobalHotKeyEvent, HotKeyState};
 use notify_rust::Notification;
 use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
-use settings::{get_non_print_key_txt, GlobalShortcuts, JsonHotKey, JsonSettings};
+use settings::{
+    GlobalShortcuts, JsonHotKey, JsonPhase, JsonPhaseSound, JsonSettings, JsonWorkHours,
+    LocalAction, LocalShortcuts,
+};
 use single_instance::SingleInstance;
 use slint::{
-    platform::Key, Model, ModelRc, PlatformError, SharedString, Timer, TimerMode, VecModel,
+    platform::Key, CloseRequestResponse, Model, ModelRc, PlatformError, SharedString, Timer,
+    TimerMode, VecModel,
 };
 use std::io::Cursor;
-use std::{borrow::Borrow, rc::Rc, str::FromStr};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    str::FromStr,
+};
 
 use log::{error, info, warn};
 
@@ -35,6 +52,25 @@ pub const ALERT_SHORT_BREAK: &[u8] = include_bytes!("../assets/audio/alert-short
 pub const ALERT_WORK: &[u8] = include_bytes!("../assets/audio/alert-work.ogg");
 pub const TICK: &[u8] = include_bytes!("../assets/audio/tick.ogg");
 
+//seconds the two snooze actions put a break off by
+const SNOOZE_SHORT: i32 = 2 * 60;
+const SNOOZE_LONG: i32 = 5 * 60;
+//no micro-break reminders in the last few minutes of a focus, the real break is close enough
+const MICRO_BREAK_QUIET: i64 = 3 * 60;
+
+//Slint doesn't expose its KeyEvent type outside of the generated code, so I can't write a function
+//taking one. This just copies the event's modifiers over into the global hotkey Modifiers.
+macro_rules! key_modifiers {
+    ($event:expr) => {{
+        let mut mods = Modifiers::empty();
+        mods.set(Modifiers::CONTROL, $event.modifiers.control);
+        mods.set(Modifiers::ALT, $event.modifiers.alt);
+        mods.set(Modifiers::SHIFT, $event.modifiers.shift);
+        mods.set(Modifiers::META, $event.modifiers.meta);
+        mods
+    }};
+}
+
 impl Main {
     fn set_settings(&self, settings: &JsonSettings) {
         self.global::<Settings>()
@@ -53,6 +89,29 @@ impl Main {
             .set_rst_ghk(settings.global_shortcuts.reset.to_string().into());
         self.global::<Settings>()
             .set_skp_ghk(settings.global_shortcuts.skip.to_string().into());
+        self.global::<Settings>()
+            .set_snz_ghk(settings.global_shortcuts.snooze.to_string().into());
+        self.global::<Settings>()
+            .set_ext_ghk(settings.global_shortcuts.extend.to_string().into());
+
+        //Window Shortcuts
+        let local = &settings.local_shortcuts;
+        for (lhk, key) in [
+            (LHKShortcuts::ToggleTimer, &local.toggle),
+            (LHKShortcuts::ResetTimer, &local.reset),
+            (LHKShortcuts::SkipRound, &local.skip),
+            (LHKShortcuts::Snooze, &local.snooze),
+            (LHKShortcuts::Extend, &local.extend),
+            (LHKShortcuts::Mute, &local.mute),
+            (LHKShortcuts::VolumeUp, &local.volume_up),
+            (LHKShortcuts::VolumeDown, &local.volume_down),
+            (LHKShortcuts::TimerTab, &local.tab_timer),
+            (LHKShortcuts::OptionsTab, &local.tab_options),
+            (LHKShortcuts::ThemesTab, &local.tab_themes),
+            (LHKShortcuts::AboutTab, &local.tab_about),
+        ] {
+            self.set_lhk_text(lhk, key.to_string().into());
+        }
 
         self.global::<Settings>()
             .set_min_to_tray(settings.min_to_tray);
@@ -75,31 +134,81 @@ impl Main {
         self.global::<Settings>()
             .set_work_rounds(settings.work_rounds);
 
+        let profiles: Vec<Profile> = settings.profiles.iter().map(Profile::from).collect();
+        self.global::<Settings>()
+            .set_profiles(ModelRc::new(VecModel::from(profiles)));
+        self.global::<Settings>()
+            .set_active_profile((&settings.active_profile).into());
+
+        let phases: Vec<Phase> = settings.phase_sequence.iter().map(Phase::from).collect();
+        self.global::<Settings>()
+            .set_phase_sequence(ModelRc::new(VecModel::from(phases)));
+        self.global::<Settings>().set_overtime(settings.overtime);
+        self.global::<Settings>()
+            .set_snooze_limit(settings.snooze_limit);
+        self.global::<Settings>().set_extend_by(settings.extend_by);
+        self.global::<Settings>()
+            .set_daily_rounds(settings.daily_rounds);
+        self.global::<Settings>()
+            .set_daily_minutes(settings.daily_minutes);
+        self.global::<Settings>()
+            .set_goal_in_minutes(settings.goal_in_minutes);
+        self.global::<Settings>()
+            .set_stop_at_target(settings.stop_at_target);
+        self.global::<Settings>().set_day_start(settings.day_start);
+        self.global::<Settings>()
+            .set_idle_threshold(settings.idle_threshold);
+        self.global::<Settings>()
+            .set_lock_action(settings.lock_action.into());
+        self.global::<Settings>()
+            .set_strict_breaks(settings.strict_breaks);
+        self.global::<Settings>()
+            .set_reflection(settings.reflection);
+        self.global::<Settings>()
+            .set_strict_skip_after(settings.strict_skip_after);
+        self.global::<Settings>()
+            .set_strict_skip_limit(settings.strict_skip_limit);
+        self.global::<Settings>()
+            .set_micro_break_every(settings.micro_break_every);
+        self.set_schedule(settings);
+
         self.global::<Settings>()
             .set_is_wayland(settings::is_wayland());
+        self.global::<Settings>()
+            .set_lock_supported(cfg!(all(unix, not(target_os = "macos"))));
+    }
+
+    //the working hours and the calendars with their meetings
+    fn set_schedule(&self, settings: &JsonSettings) {
+        let hours: Vec<SharedString> = settings
+            .work_hours
+            .iter()
+            .map(|hours| hours.to_string().into())
+            .collect();
+        self.global::<Settings>()
+            .set_work_hours(ModelRc::new(VecModel::from(hours)));
+        let calendars: Vec<SharedString> =
+            settings.calendars.iter().map(SharedString::from).collect();
+        self.global::<Settings>()
+            .set_calendars(ModelRc::new(VecModel::from(calendars)));
+        self.global::<Settings>()
+            .set_meeting_action(settings.meeting_action.into());
     }
 
     fn save_settings(&self) {
-        settings::save_settings(&JsonSettings {
+        settings::queue_save(self.as_weak(), self.json_settings());
+    }
+
+    fn json_settings(&self) -> JsonSettings {
+        let mut settings = JsonSettings {
+            version: settings::SETTINGS_VERSION,
             always_on_top: self.global::<Settings>().get_always_on_top(),
             auto_start_break_timer: self.global::<Settings>().get_auto_start_break_timer(),
             auto_start_work_timer: self.global::<Settings>().get_auto_start_work_timer(),
             break_always_on_top: self.global::<Settings>().get_break_always_on_top(),
 
-            global_shortcuts: GlobalShortcuts {
-                reset: JsonHotKey::from_str(
-                    self.global::<Settings>().get_rst_ghk().to_string().as_str(),
-                )
-                .expect("a valid Timer Reset GHK"),
-                skip: JsonHotKey::from_str(
-                    self.global::<Settings>().get_skp_ghk().to_string().as_str(),
-                )
-                .expect("a valid Timer Skip GHK"),
-                toggle: JsonHotKey::from_str(
-                    self.global::<Settings>().get_tt_ghk().to_string().as_str(),
-                )
-                .expect("a valid Timer Toggle GHK"),
-            },
+            global_shortcuts: self.global_shortcuts(),
+            local_shortcuts: self.local_shortcuts(),
 
             min_to_tray: self.global::<Settings>().get_min_to_tray(),
             min_to_tray_on_close: self.global::<Settings>().get_min_to_tray_on_close(),
@@ -114,17 +223,682 @@ impl Main {
             time_work: self.global::<Settings>().get_time_work(),
             volume: self.global::<Settings>().get_volume(),
             work_rounds: self.global::<Settings>().get_work_rounds(),
+            profiles: self
+                .global::<Settings>()
+                .get_profiles()
+                .iter()
+                .map(|profile| (&profile).into())
+                .collect(),
+            active_profile: self.global::<Settings>().get_active_profile().to_string(),
+            phase_sequence: self
+                .global::<Settings>()
+                .get_phase_sequence()
+                .iter()
+                .map(|phase| (&phase).into())
+                .collect(),
+            overtime: self.global::<Settings>().get_overtime(),
+            snooze_limit: self.global::<Settings>().get_snooze_limit(),
+            extend_by: self.global::<Settings>().get_extend_by(),
+            daily_rounds: self.global::<Settings>().get_daily_rounds(),
+            daily_minutes: self.global::<Settings>().get_daily_minutes(),
+            goal_in_minutes: self.global::<Settings>().get_goal_in_minutes(),
+            stop_at_target: self.global::<Settings>().get_stop_at_target(),
+            day_start: self.global::<Settings>().get_day_start(),
+            idle_threshold: self.global::<Settings>().get_idle_threshold(),
+            lock_action: self.global::<Settings>().get_lock_action().into(),
+            strict_breaks: self.global::<Settings>().get_strict_breaks(),
+            strict_skip_after: self.global::<Settings>().get_strict_skip_after(),
+            strict_skip_limit: self.global::<Settings>().get_strict_skip_limit(),
+            micro_break_every: self.global::<Settings>().get_micro_break_every(),
+            work_hours: self.work_hours(),
+            calendars: self.calendars(),
+            meeting_action: self.global::<Settings>().get_meeting_action().into(),
+            reflection: self.global::<Settings>().get_reflection(),
+        };
+        settings.store_profile();
+        settings
+    }
+
+    //Only hours that could be read ever make it into the list, so they all read back
+    fn work_hours(&self) -> Vec<JsonWorkHours> {
+        self.global::<Settings>()
+            .get_work_hours()
+            .iter()
+            .filter_map(|hours| JsonWorkHours::parse(&hours))
+            .flatten()
+            .collect()
+    }
+
+    fn calendars(&self) -> Vec<String> {
+        self.global::<Settings>()
+            .get_calendars()
+            .iter()
+            .map(|path| path.to_string())
+            .collect()
+    }
+
+    fn global_shortcuts(&self) -> GlobalShortcuts {
+        GlobalShortcuts {
+            reset: JsonHotKey::from_str(
+                self.global::<Settings>().get_rst_ghk().to_string().as_str(),
+            )
+            .expect("a valid Timer Reset GHK"),
+            skip: JsonHotKey::from_str(
+                self.global::<Settings>().get_skp_ghk().to_string().as_str(),
+            )
+            .expect("a valid Timer Skip GHK"),
+            toggle: JsonHotKey::from_str(
+                self.global::<Settings>().get_tt_ghk().to_string().as_str(),
+            )
+            .expect("a valid Timer Toggle GHK"),
+            snooze: JsonHotKey::from_str(
+                self.global::<Settings>().get_snz_ghk().to_string().as_str(),
+            )
+            .expect("a valid Timer Snooze GHK"),
+            extend: JsonHotKey::from_str(
+                self.global::<Settings>().get_ext_ghk().to_string().as_str(),
+            )
+            .expect("a valid Timer Extend GHK"),
+        }
+    }
+
+    //Settings that change outside of the config page (the file changing on disk, switching profiles)
+    //go through the same setters as loading them at startup, then everything set_settings doesn't
+    //cover (the config toggles, the audio, the hotkeys and the theme) gets brought up to date.
+    //Nothing is saved here, that's up to the caller.
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap
+    )] //volume is 0..=100 and there are nowhere near i32::MAX themes
+    fn apply_settings(
+        &self,
+        settings: &JsonSettings,
+        config_model: &VecModel<ConfigData>,
+        sink: &Sink,
+        hotkeys: &Hotkeys,
+    ) {
+        let current = self.json_settings();
+        if *settings == current {
+            return;
+        }
+
+        self.set_settings(settings);
+
+        for row in 0..config_model.row_count() {
+            let Some(data) = config_model.row_data(row) else {
+                continue;
+            };
+            let state = match data.sett_param {
+                BoolSettTypes::AlwOnTop => settings.always_on_top,
+                BoolSettTypes::BrkAlwOnTop => settings.break_always_on_top,
+                BoolSettTypes::AutoStrtWrkTim => settings.auto_start_work_timer,
+                BoolSettTypes::AutoStrtBreakTim => settings.auto_start_break_timer,
+                BoolSettTypes::TickSounds => settings.tick_sounds,
+                BoolSettTypes::TickSoundsBreak => settings.tick_sounds_during_break,
+                BoolSettTypes::Notifications => settings.notifications,
+                BoolSettTypes::MinToTray => settings.min_to_tray,
+                BoolSettTypes::MinToTryCls => settings.min_to_tray_on_close,
+                BoolSettTypes::Overtime => settings.overtime,
+                BoolSettTypes::StopAtTarget => settings.stop_at_target,
+                BoolSettTypes::GoalInMinutes => settings.goal_in_minutes,
+                BoolSettTypes::StrictBreaks => settings.strict_breaks,
+                BoolSettTypes::Reflection => settings.reflection,
+            };
+            let enabled = if data.sett_param == BoolSettTypes::BrkAlwOnTop {
+                !settings::is_wayland() && settings.always_on_top
+            } else {
+                data.enabled
+            };
+            config_model.set_row_data(
+                row,
+                ConfigData {
+                    state,
+                    enabled,
+                    ..data
+                },
+            );
+        }
+
+        sink.set_volume(settings.volume as f32 / 100.0);
+
+        //a running timer finishes the phase it's on, the new sequence picks up from the next one
+        if settings.phase_sequence != current.phase_sequence && !self.get_running() {
+            self.restart_phases(settings);
+        }
+
+        if settings.day_start != current.day_start {
+            self.refresh_today();
+        }
+
+        if settings.global_shortcuts != current.global_shortcuts {
+            hotkeys.register(&settings.global_shortcuts);
+        }
+
+        if settings.theme != current.theme {
+            let themes = self.global::<ThemeCallbacks>().get_themes();
+            if let Some((idx, theme)) = themes
+                .iter()
+                .enumerate()
+                .find(|(_, thm)| thm.name == settings.theme)
+            {
+                settings::apply_theme(self, idx as i32, theme);
+            } else {
+                warn!(
+                    "Theme {} isn't installed, keeping the current one",
+                    settings.theme
+                );
+                self.global::<Settings>().set_theme(current.theme.into());
+            }
+        }
+    }
+
+    //Shows which phase the timer is on. The rounds count the focus phases, so the classic cycle
+    //still reads 1/4, 2/4... and a custom sequence counts its own focus phases.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)] //sequences are short
+    fn enter_phase(&self, idx: usize, phases: &[JsonPhase]) {
+        let phase = &phases[idx];
+        self.set_phase_index(idx as i32);
+        self.set_phase(phase.into());
+        self.set_phase_label(phase.name.to_uppercase().into());
+        self.set_meeting_ahead(SharedString::new());
+        self.set_meeting_checked(false);
+        self.set_shortened_by(0);
+        self.set_activity(if phase.focus {
+            SharedString::new()
+        } else {
+            activities::next(activities::is_long(phase)).into()
         });
+
+        let round = phases[..=idx].iter().filter(|phase| phase.focus).count();
+        self.set_active_round(round.max(1) as i32);
+        let custom = self.global::<Settings>().get_phase_sequence().row_count() > 0;
+        let rounds = phases.iter().filter(|phase| phase.focus).count();
+        self.set_round_count(if custom { rounds.max(1) as i32 } else { 0 });
+    }
+
+    //Same as a new sequence coming in from the settings file, a running timer carries on with the
+    //phase it's on
+    fn set_phase_sequence(&self, phases: &[JsonPhase]) {
+        let model: Vec<Phase> = phases.iter().map(Phase::from).collect();
+        self.global::<Settings>()
+            .set_phase_sequence(ModelRc::new(VecModel::from(model)));
+        let settings = self.json_settings();
+        if !self.get_running() {
+            self.restart_phases(&settings);
+        }
+        self.save_settings();
+    }
+
+    //The classic cycle's first phase already follows the focus slider, a custom one needs its own time
+    fn restart_phases(&self, settings: &JsonSettings) {
+        let phases = settings.phases();
+        self.enter_phase(0, &phases);
+        if !settings.phase_sequence.is_empty() {
+            let first_time = i64::from(phases[0].seconds) * 1000;
+            self.set_target_time(first_time);
+            self.set_remaining_time(first_time);
+        }
+    }
+
+    //Adds to the phase that's running. Target and remaining both grow so the ring stays right, and
+    //a focus phase in overtime goes back to counting down.
+    fn extend(&self, seconds: i32) {
+        let extra = i64::from(seconds) * 1000;
+        self.set_target_time(self.get_target_time() + extra);
+        self.set_remaining_time(self.get_remaining_time() + extra);
+        //the longer focus might run into a meeting now
+        self.set_meeting_checked(false);
+    }
+
+    //Puts the break off for a few more minutes of whatever came before it. If the break has already
+    //started the timer goes back a phase, so the break comes around again once the snooze is up.
+    //Returns false once the break has been snoozed as many times as it's allowed.
+    fn snooze(&self, seconds: i32) -> bool {
+        if !self.get_can_snooze() {
+            info!("The break has already been snoozed as many times as it can be, or it's strict");
+            return false;
+        }
+        self.set_snoozes(self.get_snoozes() + 1);
+
+        if self.get_phase().focus {
+            self.extend(seconds);
+        } else {
+            //Whatever ran of the break before it was snoozed still gets recorded, the same as
+            //change_timer would. It's only put off, so it doesn't count as skipped.
+            let overtime = self.get_overtime();
+            let elapsed = self.get_target_time() - self.get_remaining_time() + overtime;
+            if elapsed >= 1000 {
+                history::record(&history::Session::ended_now(
+                    &(&self.get_phase()).into(),
+                    self.get_shortened_by() / 1000,
+                    elapsed / 1000,
+                    overtime / 1000,
+                    false,
+                ));
+            }
+            self.set_overtime(0);
+
+            let phases = self.json_settings().phases();
+            let idx = usize::try_from(self.get_phase_index()).unwrap_or(0);
+            self.enter_phase((idx + phases.len() - 1) % phases.len(), &phases);
+            let snooze_time = i64::from(seconds) * 1000;
+            self.set_target_time(snooze_time);
+            self.set_remaining_time(snooze_time);
+        }
+        true
+    }
+
+    //Reads today's rounds and focus time back from the history. The day can turn over while
+    //running, so this is redone whenever a phase ends or the timer is started.
+    fn refresh_today(&self) {
+        let day_start = u32::try_from(self.global::<Settings>().get_day_start()).unwrap_or(0);
+        let totals = history::day_totals(day_start);
+        self.set_rounds_today(i32::try_from(totals.rounds).unwrap_or(i32::MAX));
+        self.set_focus_today(i32::try_from(totals.focus).unwrap_or(i32::MAX));
+        self.set_skips_today(i32::try_from(totals.skipped_breaks).unwrap_or(i32::MAX));
+        self.set_rating_today(totals.average_rating().unwrap_or(0.0));
+    }
+
+    //only counts what has been recorded, not the focus that's running
+    fn goal_met(&self) -> bool {
+        let settings = self.global::<Settings>();
+        if settings.get_goal_in_minutes() {
+            settings.get_daily_minutes() > 0
+                && self.get_focus_today() >= settings.get_daily_minutes() * 60
+        } else {
+            settings.get_daily_rounds() > 0
+                && self.get_rounds_today() >= settings.get_daily_rounds()
+        }
+    }
+
+    fn skips_left(&self) -> i32 {
+        (self.global::<Settings>().get_strict_skip_limit() - self.get_skips_today()).max(0)
+    }
+
+    //Seconds until a strict break can be skipped with a click, or -1 if it has to be held for
+    fn skip_in(&self) -> i32 {
+        let after = self.global::<Settings>().get_strict_skip_after();
+        if after <= 0 {
+            return -1;
+        }
+        let elapsed = (self.get_target_time() - self.get_remaining_time()) / 1000;
+        (after - i32::try_from(elapsed).unwrap_or(i32::MAX)).max(0)
+    }
+
+    //Anything can skip a break that isn't strict. A strict one needs a skip left for the day, and
+    //either to have been held for or to have run long enough.
+    fn can_skip(&self, held: bool) -> bool {
+        if !self.global::<Settings>().get_strict_breaks() || self.get_phase().focus {
+            return true;
+        }
+        self.skips_left() > 0 && (held || self.skip_in() == 0)
+    }
+
+    //from the break overlay
+    fn skip_break(&self, held: bool) {
+        if self.get_phase().focus || !self.can_skip(held) {
+            return;
+        }
+        info!("Skipping the strict break");
+        self.set_skipping(true);
+        self.invoke_change_timer();
+    }
+
+    //Every micro_break_every seconds into a focus, unless the real break is only a few minutes off
+    fn micro_break_due(&self) -> bool {
+        let every = i64::from(self.global::<Settings>().get_micro_break_every());
+        if every <= 0 || !self.get_running() || !self.get_phase().focus {
+            return false;
+        }
+        let elapsed = (self.get_target_time() - self.get_remaining_time()) / 1000;
+        elapsed > 0 && elapsed % every == 0 && self.get_remaining_time() > MICRO_BREAK_QUIET * 1000
+    }
+
+    //Looks once per focus (and again if it's extended) for a meeting starting before it ends
+    fn check_meeting_ahead(&self) {
+        if self.get_meeting_checked()
+            || !self.get_running()
+            || !self.get_phase().focus
+            || calendar::attending().is_some()
+        {
+            return;
+        }
+        self.set_meeting_checked(true);
+
+        let now = Local::now().naive_local();
+        let ends = now + TimeDelta::milliseconds(self.get_remaining_time());
+        let Some(meeting) = calendar::next_starting(ends) else {
+            return;
+        };
+        let starts_in = (meeting.start - now).num_seconds();
+        info!(
+            "{} starts in {starts_in}s, during this focus",
+            meeting.summary
+        );
+        self.set_meeting_ahead(meeting.summary.clone().into());
+        self.set_meeting_in(i32::try_from(starts_in).unwrap_or(i32::MAX));
+        if self.global::<Settings>().get_notifications() {
+            if let Err(e) = Notification::new()
+                .summary("Meeting Coming Up")
+                .body(&format!(
+                    "{} starts at {}, before this focus ends.",
+                    meeting.summary,
+                    meeting.start.format("%H:%M")
+                ))
+                .show()
+            {
+                error!("Unable to show the notification: {e}");
+            }
+        }
+    }
+
+    //Called every calendar::CHECK. A meeting starting pauses the timer, or with Skip ends the
+    //focus so the meeting stands in for the break. Once it's over the timer picks up again, with
+    //Skip also going past the break the meeting replaced.
+    fn meeting_update(&self) {
+        let current = calendar::current();
+        let attending = calendar::attending();
+        if current == attending {
+            return;
+        }
+        let action = self.global::<Settings>().get_meeting_action();
+
+        if let Some(left) = attending.and_then(|_| calendar::leave()) {
+            let elapsed = (Local::now().naive_local() - left.since).num_seconds();
+            info!("{} is over", left.meeting.summary);
+            history::record(&history::Session::meeting(
+                &left.meeting.summary,
+                left.meeting.seconds(),
+                elapsed,
+            ));
+            //straight into another one, the timer stays as it is
+            if current.is_none() && left.running && action != MeetingAction::KeepRunning {
+                let paused = self.get_phase_index();
+                if action == MeetingAction::Skip && !self.get_phase().focus {
+                    self.invoke_change_timer();
+                }
+                self.resume(paused);
+            }
+            if let Some(next) = current {
+                calendar::attend(next, left.running);
+            }
+            return;
+        }
+
+        let Some(meeting) = current else {
+            return;
+        };
+        info!("{} has started", meeting.summary);
+        let running = self.get_running();
+        calendar::attend(meeting, running);
+        self.set_meeting_ahead(SharedString::new());
+        if !running || action == MeetingAction::KeepRunning {
+            return;
+        }
+        if action == MeetingAction::Skip && self.get_phase().focus {
+            self.invoke_change_timer();
+        }
+        if self.get_running() {
+            self.invoke_action_timer(TimerAction::Stop);
+        }
+    }
+
+    //Called every idle::POLL with how long it's been since any input. A running focus pauses once
+    //that passes the threshold, keeping track of how much of it went by while nobody was there.
+    //When they're back the window comes up to ask whether to keep that time.
+    fn idle_update(&self, idle: std::time::Duration) {
+        if self.get_idle_paused() {
+            if idle < idle::POLL * 2 {
+                self.set_idle_paused(false);
+                if self.get_idle_away() > 0 {
+                    self.restore();
+                }
+            }
+            return;
+        }
+
+        let threshold = self.global::<Settings>().get_idle_threshold();
+        if threshold <= 0 || !self.get_running() || !self.get_phase().focus {
+            return;
+        }
+        let idle = i64::try_from(idle.as_secs()).unwrap_or(i64::MAX);
+        if idle < i64::from(threshold) {
+            return;
+        }
+
+        //only what this focus has counted can be taken back off it
+        let focused =
+            (self.get_target_time() - self.get_remaining_time() + self.get_overtime()) / 1000;
+        info!("Idle for {idle}s, pausing the focus");
+        self.invoke_action_timer(TimerAction::Stop);
+        self.set_idle_paused(true);
+        self.set_idle_away(i32::try_from(idle.min(focused)).unwrap_or(0));
+    }
+
+    //The screen locking or the computer going to sleep. Pausing picks back up where it left off
+    //once they're back. Taking a break moves a running focus on to its break and stops the timer
+    //while they're gone, since it doesn't tick during sleep, then counts the time away towards
+    //the break when they return.
+    fn lock_changed(&self, reason: lock::Reason, locked: bool) {
+        let action = self.global::<Settings>().get_lock_action();
+        if locked {
+            if !lock::locked(reason, self.get_running()) {
+                return;
+            }
+            info!("{reason:?} started");
+            if !self.get_running() || action == LockAction::KeepRunning {
+                return;
+            }
+            if action == LockAction::TakeBreak && self.get_phase().focus {
+                self.invoke_change_timer();
+            }
+            if self.get_running() {
+                self.invoke_action_timer(TimerAction::Stop);
+            }
+            return;
+        }
+
+        let Some(away) = lock::unlocked(reason) else {
+            return;
+        };
+        let seconds = away.seconds();
+        info!("Back after {seconds}s");
+        history::record(&history::Session::away(seconds));
+        if !away.running {
+            return;
+        }
+        //The timer was left running, but it can't tick while the computer's asleep. Whatever that
+        //time finishes off carries on the way it would have if it had ticked.
+        if action == LockAction::KeepRunning {
+            if away.slept() > 0 && self.get_running() {
+                self.invoke_tick(away.slept().saturating_mul(1000));
+            }
+            return;
+        }
+
+        let paused = self.get_phase_index();
+        if action == LockAction::TakeBreak && !self.get_phase().focus {
+            self.invoke_tick(seconds.saturating_mul(1000));
+        }
+        self.resume(paused);
+    }
+
+    //Starts the timer again after it was stopped for a meeting or time away. If the phase it was
+    //stopped in has since ended, the next one only starts if it would have by itself. Either way a
+    //focus waits if the working hours have ended.
+    fn resume(&self, paused: i32) {
+        let phase = self.get_phase();
+        let carry_on = if paused == self.get_phase_index() {
+            !phase.focus || schedule::allows(&self.work_hours(), self.get_remaining_time() / 1000)
+        } else {
+            self.auto_starts(&phase)
+        };
+        if carry_on && !self.get_running() {
+            self.invoke_action_timer(TimerAction::Start);
+        }
+    }
+
+    //Whether a phase starts by itself when the one before it ends
+    fn auto_starts(&self, phase: &Phase) -> bool {
+        let settings = self.global::<Settings>();
+        if phase.focus {
+            settings.get_auto_start_work_timer()
+                && !(self.goal_met() && settings.get_stop_at_target())
+                && schedule::allows(&self.work_hours(), i64::from(phase.seconds))
+        } else {
+            //a strict break covers the screens, so there'd be no getting to the start button
+            settings.get_auto_start_break_timer() || settings.get_strict_breaks()
+        }
+    }
+
+    //brings the window back whether it was minimized or hidden away in the tray
+    fn restore(&self) {
+        if let Err(e) = self.show() {
+            error!("Unable to restore the window: {e}");
+            return;
+        }
+        self.window().set_minimized(false);
+        i_slint_backend_winit::WinitWindowAccessor::with_winit_window(
+            self.window(),
+            i_slint_backend_winit::winit::window::Window::focus_window,
+        );
+    }
+
+    fn quit(&self) {
+        self.save_settings();
+        if let Err(e) = slint::quit_event_loop() {
+            error!("Unable to quit the event loop: {e}");
+        }
+    }
+
+    fn tray_status(&self) -> TrayStatus {
+        let remaining = if self.get_in_overtime() {
+            let overtime = self.get_overtime() / 1000;
+            format!("+{:02}:{:02}", overtime / 60, overtime % 60)
+        } else {
+            let remaining = self.get_remaining_time() / 1000;
+            format!("{}:{:02}", remaining / 60, remaining % 60)
+        };
+        TrayStatus {
+            phase: self.get_phase().name.to_string(),
+            remaining,
+            running: self.get_running(),
+            muted: self.global::<Settings>().get_volume() == 0,
+            progress: self.get_progress(),
+            color: self.get_phase_color(),
+            profile: self.global::<Settings>().get_active_profile().to_string(),
+            extend: format!(
+                "Extend by {} Minutes",
+                minutes_text(self.global::<Settings>().get_extend_by())
+            ),
+        }
+    }
+
+    fn lhk_text(&self, lhk: LHKShortcuts) -> SharedString {
+        let settings = self.global::<Settings>();
+        match lhk {
+            LHKShortcuts::ToggleTimer => settings.get_tt_lhk(),
+            LHKShortcuts::ResetTimer => settings.get_rst_lhk(),
+            LHKShortcuts::SkipRound => settings.get_skp_lhk(),
+            LHKShortcuts::Snooze => settings.get_snz_lhk(),
+            LHKShortcuts::Extend => settings.get_ext_lhk(),
+            LHKShortcuts::Mute => settings.get_mute_lhk(),
+            LHKShortcuts::VolumeUp => settings.get_volup_lhk(),
+            LHKShortcuts::VolumeDown => settings.get_voldn_lhk(),
+            LHKShortcuts::TimerTab => settings.get_tmrtab_lhk(),
+            LHKShortcuts::OptionsTab => settings.get_cfgtab_lhk(),
+            LHKShortcuts::ThemesTab => settings.get_thmtab_lhk(),
+            LHKShortcuts::AboutTab => settings.get_abttab_lhk(),
+        }
+    }
+
+    fn set_lhk_text(&self, lhk: LHKShortcuts, text: SharedString) {
+        let settings = self.global::<Settings>();
+        match lhk {
+            LHKShortcuts::ToggleTimer => settings.set_tt_lhk(text),
+            LHKShortcuts::ResetTimer => settings.set_rst_lhk(text),
+            LHKShortcuts::SkipRound => settings.set_skp_lhk(text),
+            LHKShortcuts::Snooze => settings.set_snz_lhk(text),
+            LHKShortcuts::Extend => settings.set_ext_lhk(text),
+            LHKShortcuts::Mute => settings.set_mute_lhk(text),
+            LHKShortcuts::VolumeUp => settings.set_volup_lhk(text),
+            LHKShortcuts::VolumeDown => settings.set_voldn_lhk(text),
+            LHKShortcuts::TimerTab => settings.set_tmrtab_lhk(text),
+            LHKShortcuts::OptionsTab => settings.set_cfgtab_lhk(text),
+            LHKShortcuts::ThemesTab => settings.set_thmtab_lhk(text),
+            LHKShortcuts::AboutTab => settings.set_abttab_lhk(text),
+        }
+    }
+
+    //The shortcut strings are validated before they are stored in the Settings global, but if
+    //one somehow doesn't parse fall back to the default instead of losing the shortcut
+    fn local_shortcuts(&self) -> LocalShortcuts {
+        let defaults = LocalShortcuts::default();
+        let parse =
+            |lhk, default| JsonHotKey::from_str(self.lhk_text(lhk).as_str()).unwrap_or(default);
+
+        LocalShortcuts {
+            toggle: parse(LHKShortcuts::ToggleTimer, defaults.toggle),
+            reset: parse(LHKShortcuts::ResetTimer, defaults.reset),
+            skip: parse(LHKShortcuts::SkipRound, defaults.skip),
+            snooze: parse(LHKShortcuts::Snooze, defaults.snooze),
+            extend: parse(LHKShortcuts::Extend, defaults.extend),
+            mute: parse(LHKShortcuts::Mute, defaults.mute),
+            volume_up: parse(LHKShortcuts::VolumeUp, defaults.volume_up),
+            volume_down: parse(LHKShortcuts::VolumeDown, defaults.volume_down),
+            tab_timer: parse(LHKShortcuts::TimerTab, defaults.tab_timer),
+            tab_options: parse(LHKShortcuts::OptionsTab, defaults.tab_options),
+            tab_themes: parse(LHKShortcuts::ThemesTab, defaults.tab_themes),
+            tab_about: parse(LHKShortcuts::AboutTab, defaults.tab_about),
+        }
+    }
+}
+
+//The global shortcuts can change while running, so this keeps track of what is currently registered
+//to be able to release them again before registering the new ones
+struct Hotkeys {
+    manager: GlobalHotKeyManager,
+    registered: RefCell<Vec<HotKey>>,
+}
+
+impl Hotkeys {
+    fn new(shortcuts: &GlobalShortcuts) -> Self {
+        let hotkeys = Self {
+            manager: GlobalHotKeyManager::new().unwrap(),
+            registered: RefCell::new(Vec::new()),
+        };
+        hotkeys.register(shortcuts);
+        hotkeys
+    }
+
+    fn register(&self, shortcuts: &GlobalShortcuts) {
+        let mut registered = self.registered.borrow_mut();
+        if let Err(e) = self.manager.unregister_all(&registered) {
+            error!("Unable to release the old global shortcuts: {e}");
+        }
+        registered.clear();
+
+        for shortcut in [
+            &shortcuts.toggle,
+            &shortcuts.reset,
+            &shortcuts.skip,
+            &shortcuts.snooze,
+            &shortcuts.extend,
+        ] {
+            let hotkey: HotKey = shortcut.into();
+            match self.manager.register(hotkey) {
+                Ok(()) => registered.push(hotkey),
+                Err(e) => warn!("Unable to register the global shortcut {shortcut}: {e}"),
+            }
+        }
     }
 }
 
 struct Tomotroid {
     pub window: Main,
     settings: JsonSettings,
-    reset: Option<HotKey>,
-    skip: Option<HotKey>,
-    toggle: Option<HotKey>,
-    ghk_manager: GlobalHotKeyManager,
+    hotkeys: Rc<Hotkeys>,
     audio_stream: OutputStream,
     audio_handle: OutputStreamHandle,
     audio_sink: Rc<Sink>,
@@ -132,20 +906,26 @@ struct Tomotroid {
 }
 
 impl Tomotroid {
-    fn new() -> Self {
-        let settings = settings::load_settings();
+    fn new(profile: Option<&str>) -> Self {
+        let import_notice = import::first_run_import();
+        let (mut settings, notice) = settings::load_settings();
+        //a request left behind from before is stale, this one's on the command line
+        let _ = settings::take_profile_request();
+        if let Some(name) = profile {
+            match settings::find_profile(&settings, name) {
+                Ok(idx) => {
+                    settings.switch_profile(idx);
+                    info!("Switched to the {name} profile");
+                    if let Err(e) = settings::save_settings(&settings) {
+                        error!("Unable to save the switch to the {name} profile: {e}");
+                    }
+                }
+                Err(e) => warn!("{e}"),
+            }
+        }
         let themes = settings::load_themes();
 
-        let ghk_manager = GlobalHotKeyManager::new().unwrap();
-        let toggle = &settings.global_shortcuts.toggle.borrow().into();
-        let reset = &settings.global_shortcuts.reset.borrow().into();
-        let skip = &settings.global_shortcuts.skip.borrow().into();
-
-        let toggle = ghk_manager
-            .register(*toggle)
-            .map_or(None, |()| Some(*toggle));
-        let reset = ghk_manager.register(*reset).map_or(None, |()| Some(*reset));
-        let skip = ghk_manager.register(*skip).map_or(None, |()| Some(*skip));
+        let hotkeys = Rc::new(Hotkeys::new(&settings.global_shortcuts));
 
         let (audio_stream, audio_handle) = OutputStream::try_default().unwrap();
         let audio_sink = Rc::new(Sink::try_new(&audio_handle).unwrap());
@@ -153,13 +933,35 @@ impl Tomotroid {
 
         let window = Main::new().unwrap();
         window.set_settings(&settings);
+        window.restart_phases(&settings);
+        window.refresh_today();
+        if let Some(notice) = notice.or(import_notice) {
+            window.set_notice(notice.into());
+        }
 
         let theme_model: Rc<VecModel<JsonTheme>> = Rc::new(VecModel::from(themes));
         window
             .global::<ThemeCallbacks>()
             .set_themes(ModelRc::from(theme_model.clone()));
 
-        let config_model = Rc::new(VecModel::from(vec![
+        let config_model = Rc::new(Self::config_model(&settings));
+
+        //window.global::<ConfigCallbacks>().set_configs(ModelRc::new(config_model.clone().filter(|cf| cf.enabled)));
+
+        Self {
+            window,
+            settings,
+            hotkeys,
+            audio_stream,
+            audio_handle,
+            audio_sink,
+            config_model,
+        }
+    }
+
+    //the rows of toggles on the config page, in the order BoolSettTypes::to_usize expects
+    fn config_model(settings: &JsonSettings) -> VecModel<ConfigData> {
+        VecModel::from(vec![
             ConfigData {
                 name: "Always On Top".into(),
                 state: settings.always_on_top,
@@ -176,78 +978,67 @@ impl Tomotroid {
                 animate_in: false,
                 animate_out: false,
             }, //only shown when "Always On Top" is selected
-            ConfigData {
-                name: "Auto-start Work Timer".into(),
-                state: settings.auto_start_work_timer,
-                sett_param: BoolSettTypes::AutoStrtWrkTim,
-                enabled: true,
-                animate_in: false,
-                animate_out: false,
-            },
-            ConfigData {
-                name: "Auto-start Break Timer".into(),
-                state: settings.auto_start_break_timer,
-                sett_param: BoolSettTypes::AutoStrtBreakTim,
-                enabled: true,
-                animate_in: false,
-                animate_out: false,
-            },
-            ConfigData {
-                name: "Tick Sounds - Work".into(),
-                state: settings.tick_sounds,
-                sett_param: BoolSettTypes::TickSounds,
-                enabled: true,
-                animate_in: false,
-                animate_out: false,
-            },
-            ConfigData {
-                name: "Tick Sounds - Break".into(),
-                state: settings.tick_sounds_during_break,
-                sett_param: BoolSettTypes::TickSoundsBreak,
-                enabled: true,
-                animate_in: false,
-                animate_out: false,
-            },
-            ConfigData {
-                name: "Desktop Notifications".into(),
-                state: settings.notifications,
-                sett_param: BoolSettTypes::Notifications,
-                enabled: true,
-                animate_in: false,
-                animate_out: false,
-            },
-            ConfigData {
-                name: "Minimize to Tray".into(),
-                state: settings.min_to_tray,
-                sett_param: BoolSettTypes::MinToTray,
-                enabled: true,
-                animate_in: false,
-                animate_out: false,
-            },
-            ConfigData {
-                name: "Minimize to Tray on Close".into(),
-                state: settings.min_to_tray_on_close,
-                sett_param: BoolSettTypes::MinToTryCls,
-                enabled: true,
-                animate_in: false,
-                animate_out: false,
-            },
-        ]));
-
-        //window.global::<ConfigCallbacks>().set_configs(ModelRc::new(config_model.clone().filter(|cf| cf.enabled)));
-
-        Self {
-            window,
-            settings,
-            reset,
-            skip,
-            toggle,
-            ghk_manager,
-            audio_stream,
-            audio_handle,
-            audio_sink,
-            config_model,
-        }
+            toggle(
+                "Auto-start Work Timer",
+                settings.auto_start_work_timer,
+                BoolSettTypes::AutoStrtWrkTim,
+            ),
+            toggle(
+                "Auto-start Break Timer",
+                settings.auto_start_break_timer,
+                BoolSettTypes::AutoStrtBreakTim,
+            ),
+            toggle(
+                "Tick Sounds - Work",
+                settings.tick_sounds,
+                BoolSettTypes::TickSounds,
+            ),
+            toggle(
+                "Tick Sounds - Break",
+                settings.tick_sounds_during_break,
+                BoolSettTypes::TickSoundsBreak,
+            ),
+            toggle(
+                "Desktop Notifications",
+                settings.notifications,
+                BoolSettTypes::Notifications,
+            ),
+            toggle(
+                "Minimize to Tray",
+                settings.min_to_tray,
+                BoolSettTypes::MinToTray,
+            ),
+            toggle(
+                "Minimize to Tray on Close",
+                settings.min_to_tray_on_close,
+                BoolSettTypes::MinToTryCls,
+            ),
+            toggle(
+                "Overtime - Keep Focusing Past Zero",
+                settings.overtime,
+                BoolSettTypes::Overtime,
+            ),
+            toggle(
+                "Stop Auto-start at the Daily Goal",
+                settings.stop_at_target,
+                BoolSettTypes::StopAtTarget,
+            ),
+            toggle(
+                "Daily Goal in Focus Minutes",
+                settings.goal_in_minutes,
+                BoolSettTypes::GoalInMinutes,
+            ),
+            toggle(
+                "Strict Breaks - Cover Every Screen",
+                settings.strict_breaks,
+                BoolSettTypes::StrictBreaks,
+            ),
+            toggle(
+                "Reflect on Each Focus Round",
+                settings.reflection,
+                BoolSettTypes::Reflection,
+            ),
+        ])
     }
 
     fn run(&self) -> Result<(), PlatformError> {
@@ -262,7 +1053,11 @@ impl Tomotroid {
             .global::<ThemeCallbacks>()
             .invoke_theme_changed(idx as i32, cur_theme.clone());
 
-        self.window.run()
+        //The window can be hidden away in the tray, so the event loop has to keep running
+        //without any visible windows and only stop once quit is called
+        self.window.show()?;
+        slint::run_event_loop_until_quit()?;
+        self.window.hide()
     }
 }
 
@@ -279,10 +1074,149 @@ impl BoolSettTypes {
             BoolSettTypes::Notifications => 6,
             BoolSettTypes::MinToTray => 7,
             BoolSettTypes::MinToTryCls => 8,
+            BoolSettTypes::Overtime => 9,
+            BoolSettTypes::StopAtTarget => 10,
+            BoolSettTypes::GoalInMinutes => 11,
+            BoolSettTypes::StrictBreaks => 12,
+            BoolSettTypes::Reflection => 13,
+        }
+    }
+}
+
+//a row on the config page that's always there to be clicked
+fn toggle(name: &str, state: bool, sett_param: BoolSettTypes) -> ConfigData {
+    ConfigData {
+        name: name.into(),
+        state,
+        sett_param,
+        enabled: true,
+        animate_in: false,
+        animate_out: false,
+    }
+}
+
+//whole minutes read the way they always have, anything else as m:ss
+fn minutes_text(seconds: i32) -> String {
+    if seconds % 60 == 0 {
+        (seconds / 60).to_string()
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+//There's no sound of its own for meeting the goal yet, so the long break alert plays again after
+//whatever the phase change played
+fn celebrate(main: &Main, sink: &Sink) {
+    let settings = main.global::<Settings>();
+    let goal = if settings.get_goal_in_minutes() {
+        format!("{} minutes of focus", settings.get_daily_minutes())
+    } else {
+        format!("{} rounds", settings.get_daily_rounds())
+    };
+    info!("Daily goal of {goal} met");
+
+    let source = Decoder::new(Cursor::new(ALERT_LONG_BREAK)).unwrap();
+    sink.append(source);
+    if let Err(e) = Notification::new()
+        .summary("Daily Goal Reached")
+        .body(&format!("That's {goal} done today, nice work!"))
+        .show()
+    {
+        error!("Unable to show the notification: {e}");
+    }
+}
+
+//Just the tick and, if they're turned on, a notification. The focus carries on regardless.
+fn micro_break(main: &Main, sink: &Sink) {
+    let reminder = activities::micro();
+    info!("Micro-break: {reminder}");
+
+    let source = Decoder::new(Cursor::new(TICK)).unwrap();
+    sink.append(source);
+    if main.global::<Settings>().get_notifications() {
+        if let Err(e) = Notification::new()
+            .summary("Micro-break")
+            .body(&reminder)
+            .show()
+        {
+            error!("Unable to show the notification: {e}");
         }
     }
 }
 
+//Where the notification server supports buttons (only the freedesktop one does) the break can be
+//snoozed, or the new phase extended, straight from the notification
+#[cfg(all(unix, not(target_os = "macos")))]
+fn notify(summary: &str, body: &str, snooze: bool, handle: slint::Weak<Main>) {
+    let mut notification = Notification::new();
+    //.appname("Tomotroid")
+    //.icon("../assets/logo.png")
+    notification.summary(summary).body(body);
+    if snooze {
+        notification
+            .action("snooze-short", "Snooze 2m")
+            .action("snooze-long", "Snooze 5m");
+    }
+    notification.action("extend", "Extend");
+
+    match notification.show() {
+        //waiting on the buttons blocks until the notification goes away, so it gets its own thread
+        Ok(shown) => {
+            std::thread::spawn(move || {
+                shown.wait_for_action(|action| {
+                    let action = match action {
+                        "snooze-short" => TimerAction::SnoozeShort,
+                        "snooze-long" => TimerAction::SnoozeLong,
+                        "extend" => TimerAction::Extend,
+                        //clicked on or dismissed
+                        _ => return,
+                    };
+                    let _ = handle.upgrade_in_event_loop(move |main| {
+                        main.invoke_action_timer(action);
+                    });
+                });
+            });
+        }
+        Err(e) => error!("Unable to show the notification: {e}"),
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn notify(summary: &str, body: &str, _snooze: bool, _handle: slint::Weak<Main>) {
+    if let Err(e) = Notification::new().summary(summary).body(body).show() {
+        error!("Unable to show the notification: {e}");
+    }
+}
+
+enum Command {
+    Run { profile: Option<String> },
+    ListProfiles,
+    ExportHistory,
+}
+
+//  --profile <name>   switches to the named profile, in the already running Tomotroid if there is one
+//  --list-profiles    prints the names of the saved profiles
+//  --export-history   prints the whole history as CSV, ratings and notes included
+fn command_line() -> Result<Command> {
+    let mut profile = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--profile" => {
+                profile = Some(
+                    args.next()
+                        .ok_or_else(|| anyhow::anyhow!("--profile needs the name of a profile"))?,
+                );
+            }
+            "--list-profiles" => return Ok(Command::ListProfiles),
+            "--export-history" => return Ok(Command::ExportHistory),
+            //launchers and desktop environments like to add their own, so don't refuse to start over them
+            _ => warn!("Ignoring unknown argument {arg}"),
+        }
+    }
+    Ok(Command::Run { profile })
+}
+
 //eventually I want to clean this main up and make it smaller, but for now I'll just
 //surpress this clippy warning
 #[allow(clippy::too_many_lines)]
@@ -290,19 +1224,45 @@ fn main() -> Result<()> {
     setup::logging();
     info!("Starting up");
 
+    let profile = match command_line()? {
+        Command::ListProfiles => {
+            let settings = settings::read_settings().map_err(|e| anyhow::anyhow!(e))?;
+            for profile in settings.profiles {
+                println!("{}", profile.name);
+            }
+            return Ok(());
+        }
+        Command::ExportHistory => {
+            history::export_csv(&mut std::io::stdout().lock())?;
+            return Ok(());
+        }
+        Command::Run { profile } => profile,
+    };
+    //only checked here, the switch happens once the settings are loaded in whichever Tomotroid
+    //ends up running
+    if let Some(name) = &profile {
+        let settings = settings::read_settings().map_err(|e| anyhow::anyhow!(e))?;
+        settings::find_profile(&settings, name).map_err(|e| anyhow::anyhow!(e))?;
+    }
+
     let instance = SingleInstance::new("org.vadoola.tomotroid").unwrap();
     if !instance.is_single() {
+        if let Some(name) = &profile {
+            settings::request_profile(name)?;
+            info!("Asked the running Tomotroid to switch to the {name} profile");
+            return Ok(());
+        }
         error!("Only one instance of Tomotroid is allowed to run");
         return Err(anyhow::anyhow!(
             "Only one instance of Tomotroid is allowed to run"
         ));
     }
 
-    let tray_rx = setup::tray().unwrap();
+    let (tray_rx, tray_tx) = setup::tray().unwrap();
 
     setup::backend();
 
-    let tomotroid = Tomotroid::new();
+    let tomotroid = Tomotroid::new(profile.as_deref());
     let config_model = tomotroid.config_model.clone();
     let set_handle = tomotroid.window.as_weak();
     let filt_mod = Rc::new(ModelRc::from(tomotroid.config_model.clone()).filter(|cf| cf.enabled));
@@ -318,36 +1278,58 @@ fn main() -> Result<()> {
             settings::bool_changed(&set_handle, &config_model, set_type, val);
         });
 
+    //The receiver blocks until a hotkey actually fires, so nothing gets scheduled on the event loop
+    //while idle. The stop channel is dropped once the window closes which wakes the select and lets
+    //the thread exit instead of being left blocked on the receiver.
     let ghk_handle = tomotroid.window.as_weak();
-    let ghk_receiver = GlobalHotKeyEvent::receiver();
-    let _thread = std::thread::spawn(move || loop {
-        let ghk_handle2 = ghk_handle.clone();
-        slint::invoke_from_event_loop(move || {
-            if let Ok(event) = ghk_receiver.try_recv() {
-                if event.state() == global_hotkey::HotKeyState::Released {
-                    let ghk_handle2 = ghk_handle2.upgrade().unwrap();
-                    match event.id() {
-                        tg_id if tomotroid.toggle.is_some_and(|toggle| toggle.id() == tg_id) => {
-                            let action = if ghk_handle2.get_running() {
+    let (ghk_stop_tx, ghk_stop_rx) = crossbeam_channel::bounded::<()>(0);
+    let ghk_thread = std::thread::spawn(move || {
+        let ghk_receiver = GlobalHotKeyEvent::receiver();
+        loop {
+            crossbeam_channel::select! {
+                recv(ghk_receiver) -> event => {
+                    let Ok(event) = event else { break };
+                    if event.state() != HotKeyState::Released {
+                        continue;
+                    }
+
+                    let ghk_handle = ghk_handle.clone();
+                    let sent = slint::invoke_from_event_loop(move || {
+                        let Some(ghk_handle) = ghk_handle.upgrade() else {
+                            return;
+                        };
+                        //the shortcuts can be changed while running, so match against the current ones
+                        let shortcuts = ghk_handle.global_shortcuts();
+                        let pressed = |shortcut: &JsonHotKey| {
+                            Into::<HotKey>::into(shortcut).id() == event.id()
+                        };
+                        if pressed(&shortcuts.toggle) {
+                            let action = if ghk_handle.get_running() {
                                 TimerAction::Stop
                             } else {
                                 TimerAction::Start
                             };
-                            ghk_handle2.invoke_action_timer(action);
-                        }
-                        rst_id if tomotroid.reset.is_some_and(|reset| reset.id() == rst_id) => {
-                            ghk_handle2.invoke_action_timer(TimerAction::Reset);
+                            ghk_handle.invoke_action_timer(action);
+                        } else if pressed(&shortcuts.reset) {
+                            ghk_handle.invoke_action_timer(TimerAction::Reset);
+                        } else if pressed(&shortcuts.skip) {
+                            ghk_handle.invoke_action_timer(TimerAction::Skip);
+                        } else if pressed(&shortcuts.snooze) {
+                            ghk_handle.invoke_action_timer(TimerAction::SnoozeShort);
+                        } else if pressed(&shortcuts.extend) {
+                            ghk_handle.invoke_action_timer(TimerAction::Extend);
                         }
-                        skp_id if tomotroid.skip.is_some_and(|skip| skip.id() == skp_id) => {
-                            ghk_handle2.invoke_action_timer(TimerAction::Skip);
-                        }
-                        _ => {}
+                    });
+
+                    //the event loop has already quit, so there is nobody left to send hotkeys to
+                    if sent.is_err() {
+                        break;
                     }
                 }
+                recv(ghk_stop_rx) -> _ => break,
             }
-        })
-        .unwrap();
-        std::thread::sleep(std::time::Duration::from_millis(500));
+        }
+        info!("Global hotkey thread shutting down");
     });
 
     let vol_sink = tomotroid.audio_sink.clone();
@@ -359,22 +1341,40 @@ fn main() -> Result<()> {
             settings::int_changed(&set_int_handle, &vol_sink, set_type, val);
         });
 
+    tomotroid
+        .window
+        .global::<Settings>()
+        .on_parse_duration(|text| settings::parse_duration(&text).unwrap_or(0));
+
     let close_handle = tomotroid.window.as_weak();
     tomotroid.window.on_close_window(move || {
         let close_handle = close_handle.upgrade().unwrap();
-        close_handle.save_settings();
-
-        close_handle.hide().unwrap();
+        if close_handle.global::<Settings>().get_min_to_tray_on_close() {
+            close_handle.save_settings();
+            close_handle.hide().unwrap();
+        } else {
+            close_handle.quit();
+        }
+    });
 
-        //After I get the system tray working I'm going to want to hide the window instead of actually close it
-        //if it's set to hide on close
-        //i_slint_backend_winit::WinitWindowAccessor::with_winit_window(min_handle.window(), |win| win.set_visible(false));
+    //The title bar is drawn by Slint, but the window manager can still ask the window to close
+    //(Alt+F4 etc.) so send that down the same path as the close button
+    let close_req_handle = tomotroid.window.as_weak();
+    tomotroid.window.window().on_close_requested(move || {
+        if let Some(main) = close_req_handle.upgrade() {
+            main.invoke_close_window();
+        }
+        CloseRequestResponse::KeepWindowShown
     });
 
     let min_handle = tomotroid.window.as_weak();
     tomotroid.window.on_minimize_window(move || {
         let min_handle = min_handle.upgrade().unwrap();
-        min_handle.window().set_minimized(true);
+        if min_handle.global::<Settings>().get_min_to_tray() {
+            min_handle.hide().unwrap();
+        } else {
+            min_handle.window().set_minimized(true);
+        }
     });
 
     let move_handle = tomotroid.window.as_weak();
@@ -387,34 +1387,57 @@ fn main() -> Result<()> {
     });
 
     let tray_handle = tomotroid.window.as_weak();
-    let _tray_rec_thread = std::thread::spawn(move || loop {
-        match tray_rx.recv() {
-            Ok(TrayMsg::MinRes) => {
-                let tray_handle_copy = tray_handle.clone();
-                slint::invoke_from_event_loop(move || {
-                    let main = tray_handle_copy.upgrade().unwrap();
-                    main.window().set_minimized(false);
-                    i_slint_backend_winit::WinitWindowAccessor::with_winit_window(
-                        main.window(),
-                        |win| {
-                            //win.set_minimized(!win.is_minimized().unwrap());
-                            win.focus_window();
-                        },
-                    );
-                })
-                .unwrap();
-            }
-            Ok(TrayMsg::Quit) => {
-                let tray_handle_copy = tray_handle.clone();
-                slint::invoke_from_event_loop(move || {
-                    tray_handle_copy.upgrade().unwrap().hide().unwrap();
-                })
-                .unwrap();
-            }
-            _ => {}
+    let _tray_rec_thread = std::thread::spawn(move || {
+        while let Ok(msg) = tray_rx.recv() {
+            let tray_handle = tray_handle.clone();
+            let sent = slint::invoke_from_event_loop(move || {
+                let Some(main) = tray_handle.upgrade() else {
+                    return;
+                };
+                match msg {
+                    TrayMsg::MinRes => {
+                        if main.window().is_visible() && !main.window().is_minimized() {
+                            main.invoke_minimize_window();
+                        } else {
+                            main.restore();
+                        }
+                    }
+                    TrayMsg::Quit => main.quit(),
+                    TrayMsg::Toggle => {
+                        let action = if main.get_running() {
+                            TimerAction::Stop
+                        } else {
+                            TimerAction::Start
+                        };
+                        main.invoke_action_timer(action);
+                    }
+                    TrayMsg::Reset => main.invoke_action_timer(TimerAction::Reset),
+                    TrayMsg::Skip => main.invoke_action_timer(TimerAction::Skip),
+                    TrayMsg::SnoozeShort => main.invoke_action_timer(TimerAction::SnoozeShort),
+                    TrayMsg::SnoozeLong => main.invoke_action_timer(TimerAction::SnoozeLong),
+                    TrayMsg::Extend => main.invoke_action_timer(TimerAction::Extend),
+                    TrayMsg::Mute => main.invoke_toggle_mute(),
+                    TrayMsg::NextProfile => main.global::<Settings>().invoke_cycle_profile(1),
+                }
+            });
+
+            //the event loop is gone, so there is nothing left to pass the tray messages on to
+            if sent.is_err() {
+                break;
+            }
         }
     });
 
+    let status_handle = tomotroid.window.as_weak();
+    tomotroid.window.on_status_changed(move || {
+        let Some(main) = status_handle.upgrade() else {
+            return;
+        };
+        //if the tray thread is gone there is nothing to update, the window still works without it
+        let _ = tray_tx.send(main.tray_status());
+    });
+    tomotroid.window.invoke_status_changed();
+
     tomotroid.window.global::<HLClick>().on_hl_clicked(|url| {
         open::that(url.as_str()).unwrap();
     });
@@ -445,8 +1468,27 @@ fn main() -> Result<()> {
         let tmrstrt_handle = timer_handle.clone();
         let timer_handle = timer_handle.upgrade().unwrap();
         let tick_sink = tick_sink.clone();
+
+        //Snoozing means getting back to work, so a break that was waiting to be started gets the
+        //timer going again. This callback can't invoke itself, so it carries on as a start instead.
+        let action = match action {
+            TimerAction::SnoozeShort | TimerAction::SnoozeLong => {
+                let seconds = if action == TimerAction::SnoozeShort {
+                    SNOOZE_SHORT
+                } else {
+                    SNOOZE_LONG
+                };
+                if !timer_handle.snooze(seconds) || timer_handle.get_running() {
+                    return;
+                }
+                TimerAction::Start
+            }
+            action => action,
+        };
+
         match action {
             TimerAction::Start => {
+                timer_handle.refresh_today();
                 timer_handle.set_running(true);
                 timer.start(
                     TimerMode::Repeated,
@@ -456,8 +1498,7 @@ fn main() -> Result<()> {
 
                         if tick_count >= 20 {
                             tick_count = 0;
-                            let is_work_timer =
-                                tmrstrt_handle.get_active_timer() == ActiveTimer::Focus;
+                            let is_work_timer = tmrstrt_handle.get_phase().focus;
                             if tmrstrt_handle.global::<Settings>().get_tick_sounds()
                                 && is_work_timer
                             {
@@ -476,6 +1517,11 @@ fn main() -> Result<()> {
                         }
 
                         tmrstrt_handle.invoke_tick(1000);
+                        tmrstrt_handle.check_meeting_ahead();
+                        if tmrstrt_handle.micro_break_due() {
+                            micro_break(&tmrstrt_handle, &tick_sink);
+                        }
+                        overlay::sync(&tmrstrt_handle);
                     },
                 );
             }
@@ -487,90 +1533,381 @@ fn main() -> Result<()> {
                 timer.stop();
                 timer_handle.set_running(false);
                 timer_handle.set_remaining_time(timer_handle.get_target_time());
+                timer_handle.set_overtime(0);
             }
             TimerAction::Skip => {
                 //timer_handle.set_remaining_time(0);
-                timer_handle.invoke_change_timer();
+                if timer_handle.can_skip(false) {
+                    //only strict breaks have skips to count
+                    timer_handle
+                        .set_skipping(timer_handle.global::<Settings>().get_strict_breaks());
+                    timer_handle.invoke_change_timer();
+                } else {
+                    info!("This strict break can't be skipped yet");
+                }
+            }
+            TimerAction::Extend => {
+                timer_handle.extend(timer_handle.global::<Settings>().get_extend_by());
             }
+            //turned into a start above
+            TimerAction::SnoozeShort | TimerAction::SnoozeLong => {}
         }
+        overlay::sync(&timer_handle);
     });
 
+    //when the focus round waiting on a reflection ended, so it can be matched back up with it
+    let reflect_on: Rc<Cell<Option<u64>>> = Rc::new(Cell::new(None));
+
     let tmr_change_sink = tomotroid.audio_sink.clone();
     let chg_tmr_handle = tomotroid.window.as_weak();
+    let chg_reflect_on = reflect_on.clone();
     tomotroid.window.on_change_timer(move || {
+        let notify_handle = chg_tmr_handle.clone();
         let chg_tmr_handle = chg_tmr_handle.upgrade().unwrap();
-        match chg_tmr_handle.get_active_timer() {
-            ActiveTimer::Focus => {
-                if !chg_tmr_handle
-                    .global::<Settings>()
-                    .get_auto_start_break_timer()
-                {
-                    chg_tmr_handle.invoke_action_timer(TimerAction::Stop);
+        let phases = chg_tmr_handle.json_settings().phases();
+        let finished = chg_tmr_handle.get_phase();
+        let idx =
+            (usize::try_from(chg_tmr_handle.get_phase_index()).unwrap_or(0) + 1) % phases.len();
+        let next = &phases[idx];
+
+        //the day may have turned over since the totals were last read, leaving the goal looking met
+        chg_tmr_handle.refresh_today();
+        let goal_was_met = chg_tmr_handle.goal_met();
+
+        //a reflection left unanswered through the whole break isn't coming
+        chg_tmr_handle.set_reflecting(false);
+        chg_reflect_on.set(None);
+
+        //the phase ended before anyone said what to do with the idle time, so it stays counted
+        let idle_away = chg_tmr_handle.get_idle_away();
+        if idle_away > 0 {
+            history::record(&history::Session::idle(i64::from(idle_away), true));
+            chg_tmr_handle.set_idle_away(0);
+        }
+
+        //Skipping straight past a phase without running it isn't worth a record, unless it's a
+        //strict break being skipped since those count against the day's skips
+        let overtime = chg_tmr_handle.get_overtime();
+        let elapsed =
+            chg_tmr_handle.get_target_time() - chg_tmr_handle.get_remaining_time() + overtime;
+        let strict = chg_tmr_handle.global::<Settings>().get_strict_breaks();
+        let skipped = chg_tmr_handle.get_skipping();
+        chg_tmr_handle.set_skipping(false);
+        if elapsed >= 1000 || (strict && skipped && !finished.focus) {
+            let session = history::Session::ended_now(
+                &(&finished).into(),
+                chg_tmr_handle.get_shortened_by() / 1000,
+                elapsed / 1000,
+                overtime / 1000,
+                skipped,
+            );
+            history::record(&session);
+            if finished.focus && chg_tmr_handle.global::<Settings>().get_reflection() {
+                chg_reflect_on.set(Some(session.ended));
+                chg_tmr_handle.set_reflecting(true);
+            }
+        }
+        chg_tmr_handle.set_overtime(0);
+
+        chg_tmr_handle.refresh_today();
+        let goal_met = chg_tmr_handle.goal_met();
+
+        if !chg_tmr_handle.auto_starts(&next.into()) {
+            chg_tmr_handle.invoke_action_timer(TimerAction::Stop);
+        }
+
+        let alert = match next.sound {
+            JsonPhaseSound::Work => Some(ALERT_WORK),
+            JsonPhaseSound::ShortBreak => Some(ALERT_SHORT_BREAK),
+            JsonPhaseSound::LongBreak => Some(ALERT_LONG_BREAK),
+            JsonPhaseSound::None => None,
+        };
+        if let Some(alert) = alert {
+            let source = Decoder::new(Cursor::new(alert)).unwrap();
+            tmr_change_sink.append(source);
+        }
+        if goal_met && !goal_was_met {
+            celebrate(&chg_tmr_handle, &tmr_change_sink);
+        }
+
+        chg_tmr_handle.enter_phase(idx, &phases);
+        if next.focus {
+            chg_tmr_handle.set_snoozes(0);
+        }
+        let next_time = i64::from(next.seconds) * 1000;
+        chg_tmr_handle.set_target_time(next_time);
+        chg_tmr_handle.set_remaining_time(next_time);
+
+        let summary = if finished.focus {
+            "Focus Round Complete"
+        } else {
+            "Break Finished"
+        };
+        let body_str = if next.focus {
+            format!("Begin focusing for {} minutes.", minutes_text(next.seconds))
+        } else {
+            format!(
+                "Begin a {} minute {}.\n{}",
+                minutes_text(next.seconds),
+                next.name.to_lowercase(),
+                chg_tmr_handle.get_activity()
+            )
+        };
+        let snooze = !next.focus && chg_tmr_handle.get_can_snooze();
+        notify(summary, &body_str, snooze, notify_handle);
+        overlay::sync(&chg_tmr_handle);
+    });
+
+    let idle_choice_handle = tomotroid.window.as_weak();
+    tomotroid.window.on_idle_choice(move |keep| {
+        let Some(main) = idle_choice_handle.upgrade() else {
+            return;
+        };
+        let away = i64::from(main.get_idle_away()) * 1000;
+        if away == 0 {
+            return;
+        }
+
+        //discarding it takes it back off the overtime first, then puts the rest back on the clock
+        if !keep {
+            let overtime = main.get_overtime();
+            let from_overtime = overtime.min(away);
+            main.set_overtime(overtime - from_overtime);
+            main.set_remaining_time(main.get_remaining_time() + away - from_overtime);
+        }
+        history::record(&history::Session::idle(away / 1000, keep));
+        main.set_idle_away(0);
+
+        if !main.get_running() {
+            main.invoke_action_timer(TimerAction::Start);
+        }
+    });
+
+    let hours_handle = tomotroid.window.as_weak();
+    tomotroid
+        .window
+        .global::<Settings>()
+        .on_work_hours_changed(move |idx, text| {
+            let Some(main) = hours_handle.upgrade() else {
+                return;
+            };
+            let mut hours = main.work_hours();
+            let idx = usize::try_from(idx).unwrap_or(0);
+            if text.trim().is_empty() {
+                if idx < hours.len() {
+                    hours.remove(idx);
                 }
-                let body_str = if chg_tmr_handle.get_active_round()
-                    == chg_tmr_handle.get_tmr_config().rounds
-                {
-                    let source = Decoder::new(Cursor::new(ALERT_LONG_BREAK)).unwrap();
-                    tmr_change_sink.append(source);
-                    let lgbrk_time = chg_tmr_handle.get_tmr_config().lgbrk_time;
-
-                    chg_tmr_handle.set_active_round(1);
-                    chg_tmr_handle.set_active_timer(ActiveTimer::LongBreak);
-
-                    chg_tmr_handle.set_target_time(lgbrk_time);
-                    chg_tmr_handle.set_remaining_time(lgbrk_time);
-                    format!("Begin a {} minute long break.", lgbrk_time / 60000)
+            } else if let Some(parsed) = JsonWorkHours::parse(&text) {
+                //several ranges of hours become a line each
+                let idx = idx.min(hours.len());
+                let end = (idx + 1).min(hours.len());
+                hours.splice(idx..end, parsed);
+            } else {
+                warn!("Unable to read the working hours \"{text}\"");
+                return;
+            }
+
+            let hours: Vec<SharedString> =
+                hours.iter().map(|hours| hours.to_string().into()).collect();
+            main.global::<Settings>()
+                .set_work_hours(ModelRc::new(VecModel::from(hours)));
+            main.save_settings();
+        });
+
+    let phase_handle = tomotroid.window.as_weak();
+    tomotroid
+        .window
+        .global::<Settings>()
+        .on_phase_changed(move |idx, text| {
+            let Some(main) = phase_handle.upgrade() else {
+                return;
+            };
+            let mut phases = main.json_settings().phase_sequence;
+            let idx = usize::try_from(idx).unwrap_or(0);
+            if text.trim().is_empty() {
+                if idx < phases.len() {
+                    phases.remove(idx);
+                }
+            } else if let Some(phase) = JsonPhase::parse(&text, phases.get(idx)) {
+                if idx < phases.len() {
+                    phases[idx] = phase;
                 } else {
-                    let source = Decoder::new(Cursor::new(ALERT_SHORT_BREAK)).unwrap();
-                    tmr_change_sink.append(source);
-                    let shbrk_time = chg_tmr_handle.get_tmr_config().shbrk_time;
-                    chg_tmr_handle.set_active_timer(ActiveTimer::ShortBreak);
-                    chg_tmr_handle.set_target_time(shbrk_time);
-                    chg_tmr_handle.set_remaining_time(shbrk_time);
-                    format!("Begin a {} minute short break.", shbrk_time / 60000)
-                };
-                Notification::new()
-                    //.appname("Tomotroid")
-                    //.icon("../assets/logo.png")
-                    .summary("Focus Round Complete")
-                    .body(&body_str)
-                    .show()
-                    .unwrap();
-            }
-            brk_type => {
-                if !chg_tmr_handle
-                    .global::<Settings>()
-                    .get_auto_start_work_timer()
-                {
-                    chg_tmr_handle.invoke_action_timer(TimerAction::Stop);
+                    phases.push(phase);
                 }
+            } else {
+                warn!("Unable to read the step \"{text}\"");
+                return;
+            }
+            main.set_phase_sequence(&phases);
+        });
 
-                let focus_time = chg_tmr_handle.get_tmr_config().focus_time;
-                let source = Decoder::new(Cursor::new(ALERT_WORK)).unwrap();
-                tmr_change_sink.append(source);
-                chg_tmr_handle.set_active_round(if brk_type == ActiveTimer::ShortBreak {
-                    chg_tmr_handle.get_active_round() + 1
-                } else {
-                    1
-                });
-                chg_tmr_handle.set_active_timer(ActiveTimer::Focus);
-                chg_tmr_handle.set_target_time(focus_time);
-                chg_tmr_handle.set_remaining_time(focus_time);
-                Notification::new()
-                    //.appname("Tomotroid")
-                    //.icon("../assets/logo.png")
-                    .summary("Break Finished")
-                    .body(&format!(
-                        "Begin focusing for {} minutes.",
-                        focus_time / 60000
-                    ))
-                    .show()
-                    .unwrap();
+    let phase_up_handle = tomotroid.window.as_weak();
+    tomotroid
+        .window
+        .global::<Settings>()
+        .on_phase_moved_up(move |idx| {
+            let Some(main) = phase_up_handle.upgrade() else {
+                return;
+            };
+            let mut phases = main.json_settings().phase_sequence;
+            let idx = usize::try_from(idx).unwrap_or(0);
+            if idx == 0 || idx >= phases.len() {
+                return;
+            }
+            phases.swap(idx - 1, idx);
+            main.set_phase_sequence(&phases);
+        });
+
+    let calendars_handle = tomotroid.window.as_weak();
+    tomotroid
+        .window
+        .global::<Settings>()
+        .on_calendars_changed(move |idx, text| {
+            let Some(main) = calendars_handle.upgrade() else {
+                return;
+            };
+            let mut calendars = main.calendars();
+            let idx = usize::try_from(idx).unwrap_or(0);
+            let path = text.trim().to_string();
+            if path.is_empty() {
+                if idx < calendars.len() {
+                    calendars.remove(idx);
+                }
+            } else if idx < calendars.len() {
+                calendars[idx] = path;
+            } else {
+                calendars.push(path);
+            }
+
+            calendar::refresh(&calendars);
+            let calendars: Vec<SharedString> = calendars.iter().map(SharedString::from).collect();
+            main.global::<Settings>()
+                .set_calendars(ModelRc::new(VecModel::from(calendars)));
+            main.save_settings();
+        });
+
+    let meeting_action_handle = tomotroid.window.as_weak();
+    tomotroid
+        .window
+        .global::<Settings>()
+        .on_meeting_action_changed(move |action| {
+            let Some(main) = meeting_action_handle.upgrade() else {
+                return;
+            };
+            main.global::<Settings>().set_meeting_action(action);
+            main.save_settings();
+        });
+
+    let reflect_handle = tomotroid.window.as_weak();
+    tomotroid.window.on_reflect(move |rating, note| {
+        let Some(main) = reflect_handle.upgrade() else {
+            return;
+        };
+        main.set_reflecting(false);
+        let Some(ended) = reflect_on.take() else {
+            return;
+        };
+        let rating = u8::try_from(rating)
+            .ok()
+            .filter(|rating| (1..=5).contains(rating));
+        let note = note.trim();
+        if rating.is_none() && note.is_empty() {
+            return;
+        }
+        history::record(&history::Session::reflection(ended, rating, note));
+        main.refresh_today();
+    });
+
+    let meeting_choice_handle = tomotroid.window.as_weak();
+    tomotroid.window.on_meeting_choice(move |shorten| {
+        let Some(main) = meeting_choice_handle.upgrade() else {
+            return;
+        };
+        main.set_meeting_ahead(SharedString::new());
+        if !shorten {
+            return;
+        }
+
+        //the time has moved on since the warning, so it's worked out again from the meeting
+        let now = Local::now().naive_local();
+        let ends = now + TimeDelta::milliseconds(main.get_remaining_time());
+        let Some(meeting) = calendar::next_starting(ends) else {
+            return;
+        };
+        let cut = main.get_remaining_time() - (meeting.start - now).num_milliseconds();
+        info!(
+            "Shortening the focus by {}s for {}",
+            cut / 1000,
+            meeting.summary
+        );
+        main.set_shortened_by(main.get_shortened_by() + cut);
+        main.set_target_time(main.get_target_time() - cut);
+        main.set_remaining_time(main.get_remaining_time() - cut);
+    });
+
+    let meetings_handle = tomotroid.window.as_weak();
+    calendar::refresh(&tomotroid.settings.calendars);
+    let meetings_timer = Timer::default();
+    meetings_timer.start(TimerMode::Repeated, calendar::CHECK, move || {
+        let Some(main) = meetings_handle.upgrade() else {
+            return;
+        };
+        calendar::refresh(&main.calendars());
+        main.meeting_update();
+    });
+
+    //Starts the first focus when the working hours begin. Only the moment they begin counts, so
+    //starting up or pausing partway through them is left alone.
+    let schedule_handle = tomotroid.window.as_weak();
+    let was_working = std::cell::Cell::new(schedule::working_now(&tomotroid.window.work_hours()));
+    let schedule_timer = Timer::default();
+    schedule_timer.start(TimerMode::Repeated, schedule::CHECK, move || {
+        let Some(main) = schedule_handle.upgrade() else {
+            return;
+        };
+        let hours = main.work_hours();
+        let working = schedule::working_now(&hours);
+        if working
+            && !was_working.get()
+            && !main.get_running()
+            && main.get_phase().focus
+            && schedule::allows(&hours, main.get_remaining_time() / 1000)
+        {
+            info!("Working hours have started, starting the focus");
+            main.invoke_action_timer(TimerAction::Start);
+        }
+        was_working.set(working);
+    });
+
+    //Nothing to stop this one on the way out, it just finds the event loop gone on its next check
+    let idle_handle = tomotroid.window.as_weak();
+    std::thread::spawn(move || {
+        let Some(monitor) = idle::IdleMonitor::new() else {
+            return;
+        };
+        loop {
+            std::thread::sleep(idle::POLL);
+            let Some(idle) = monitor.idle_time() else {
+                continue;
+            };
+            if idle_handle
+                .upgrade_in_event_loop(move |main| main.idle_update(idle))
+                .is_err()
+            {
+                break;
             }
         }
     });
 
+    let lock_handle = tomotroid.window.as_weak();
+    lock::watch(move |reason, locked| {
+        lock_handle
+            .upgrade_in_event_loop(move |main| main.lock_changed(reason, locked))
+            .is_ok()
+    });
+
     let ghk_handle = tomotroid.window.as_weak();
+    let ghk_hotkeys = tomotroid.hotkeys.clone();
     tomotroid
         .window
         .global::<ConfigCallbacks>()
@@ -650,27 +1987,23 @@ fn main() -> Result<()> {
                             .set_skp_ghk(SharedString::new());
                         ghk_handle.global::<Settings>().set_skp_ghk(pre);
                     }
+                    GHKShortcuts::Snooze => {
+                        let pre = ghk_handle.global::<Settings>().get_snz_ghk();
+                        ghk_handle
+                            .global::<Settings>()
+                            .set_snz_ghk(SharedString::new());
+                        ghk_handle.global::<Settings>().set_snz_ghk(pre);
+                    }
+                    GHKShortcuts::Extend => {
+                        let pre = ghk_handle.global::<Settings>().get_ext_ghk();
+                        ghk_handle
+                            .global::<Settings>()
+                            .set_ext_ghk(SharedString::new());
+                        ghk_handle.global::<Settings>().set_ext_ghk(pre);
+                    }
                 }
             } else {
-                let mut text = String::new();
-                if event.modifiers.control {
-                    text = "Control+".to_string();
-                }
-                if event.modifiers.alt {
-                    text.push_str("Alt+");
-                }
-                if event.modifiers.shift {
-                    text.push_str("Shift+");
-                }
-                if event.modifiers.meta {
-                    text.push_str("Super+");
-                }
-
-                if let Some(non_pr_char) = get_non_print_key_txt(&event.text) {
-                    text.push_str(non_pr_char);
-                } else {
-                    text.push_str(&event.text.to_uppercase());
-                }
+                let text = settings::hotkey_text(key_modifiers!(event), &event.text);
 
                 match ghk {
                     GHKShortcuts::ToggleTimer => {
@@ -682,11 +2015,221 @@ fn main() -> Result<()> {
                     GHKShortcuts::SkipRound => {
                         ghk_handle.global::<Settings>().set_skp_ghk(text.into());
                     }
+                    GHKShortcuts::Snooze => {
+                        ghk_handle.global::<Settings>().set_snz_ghk(text.into());
+                    }
+                    GHKShortcuts::Extend => {
+                        ghk_handle.global::<Settings>().set_ext_ghk(text.into());
+                    }
                 }
+                ghk_hotkeys.register(&ghk_handle.global_shortcuts());
                 ghk_handle.save_settings();
             }
         });
 
-    tomotroid.run()?;
+    let lhk_handle = tomotroid.window.as_weak();
+    tomotroid
+        .window
+        .global::<ConfigCallbacks>()
+        .on_new_lhk(move |lhk, event| {
+            let lhk_handle = lhk_handle.upgrade().unwrap();
+            let text = settings::hotkey_text(key_modifiers!(event), &event.text);
+
+            //Unlike the global shortcuts these don't need a modifier, so a single key is fine.
+            //Esc and Tab just back out of editing, and anything that won't parse back into a
+            //hotkey is rejected. Same blank and reset trick as the global shortcuts to drop the focus.
+            if event.text == SharedString::from(Key::Escape)
+                || event.text == SharedString::from(Key::Tab)
+                || JsonHotKey::from_str(&text).is_err()
+            {
+                let pre = lhk_handle.lhk_text(lhk);
+                lhk_handle.set_lhk_text(lhk, SharedString::new());
+                lhk_handle.set_lhk_text(lhk, pre);
+            } else {
+                lhk_handle.set_lhk_text(lhk, text.into());
+                lhk_handle.save_settings();
+            }
+        });
+
+    let mute_handle = tomotroid.window.as_weak();
+    let mute_sink = tomotroid.audio_sink.clone();
+    let mut unmuted_volume = 100;
+    tomotroid.window.on_toggle_mute(move || {
+        let Some(main) = mute_handle.upgrade() else {
+            return;
+        };
+        let volume = main.global::<Settings>().get_volume();
+        let new_volume = if volume > 0 {
+            unmuted_volume = volume;
+            0
+        } else {
+            unmuted_volume
+        };
+        settings::int_changed(&mute_handle, &mute_sink, IntSettTypes::Volume, new_volume);
+    });
+
+    let lhk_handle = tomotroid.window.as_weak();
+    let lhk_sink = tomotroid.audio_sink.clone();
+    tomotroid.window.on_local_key(move |event| {
+        let Some(main) = lhk_handle.upgrade() else {
+            return false;
+        };
+        let Ok(pressed) =
+            JsonHotKey::from_str(&settings::hotkey_text(key_modifiers!(event), &event.text))
+        else {
+            return false;
+        };
+        let Some(action) = main.local_shortcuts().action(&pressed) else {
+            return false;
+        };
+
+        //holding a key down should keep adjusting the volume, but not flip the timer back and forth
+        if event.repeat && !matches!(action, LocalAction::VolumeUp | LocalAction::VolumeDown) {
+            return true;
+        }
+
+        let volume = main.global::<Settings>().get_volume();
+        match action {
+            LocalAction::Toggle => {
+                let action = if main.get_running() {
+                    TimerAction::Stop
+                } else {
+                    TimerAction::Start
+                };
+                main.invoke_action_timer(action);
+            }
+            LocalAction::Reset => main.invoke_action_timer(TimerAction::Reset),
+            LocalAction::Skip => main.invoke_action_timer(TimerAction::Skip),
+            LocalAction::Snooze => main.invoke_action_timer(TimerAction::SnoozeShort),
+            LocalAction::Extend => main.invoke_action_timer(TimerAction::Extend),
+            LocalAction::Mute => main.invoke_toggle_mute(),
+            LocalAction::VolumeUp => {
+                settings::int_changed(
+                    &lhk_handle,
+                    &lhk_sink,
+                    IntSettTypes::Volume,
+                    (volume + 5).min(100),
+                );
+            }
+            LocalAction::VolumeDown => {
+                settings::int_changed(
+                    &lhk_handle,
+                    &lhk_sink,
+                    IntSettTypes::Volume,
+                    (volume - 5).max(0),
+                );
+            }
+            LocalAction::Tab(page) => main.invoke_show_page(page),
+        }
+        true
+    });
+
+    let profile_handle = tomotroid.window.as_weak();
+    let profile_model = tomotroid.config_model.clone();
+    let profile_sink = tomotroid.audio_sink.clone();
+    let profile_hotkeys = tomotroid.hotkeys.clone();
+    tomotroid
+        .window
+        .global::<Settings>()
+        .on_cycle_profile(move |step| {
+            let Some(main) = profile_handle.upgrade() else {
+                return;
+            };
+            let mut settings = main.json_settings();
+            settings.switch_profile(settings.next_profile(step > 0));
+            info!("Switching to the {} profile", settings.active_profile);
+            main.apply_settings(&settings, &profile_model, &profile_sink, &profile_hotkeys);
+            main.save_settings();
+        });
+
+    let lock_action_handle = tomotroid.window.as_weak();
+    tomotroid
+        .window
+        .global::<Settings>()
+        .on_lock_action_changed(move |action| {
+            let Some(main) = lock_action_handle.upgrade() else {
+                return;
+            };
+            main.global::<Settings>().set_lock_action(action);
+            main.save_settings();
+        });
+
+    //The watcher thread can only poke the UI, the reload itself waits for the file to stop changing
+    //since editors and syncing tools tend to write it in several steps
+    let watch_handle = tomotroid.window.as_weak();
+    let _settings_watcher = settings::watch_settings(move || {
+        let _ = watch_handle.upgrade_in_event_loop(|main| main.invoke_settings_file_changed());
+    })
+    .map_err(|e| warn!("Unable to watch the settings file for changes: {e}"))
+    .ok();
+
+    let reload_handle = tomotroid.window.as_weak();
+    let reload_model = tomotroid.config_model.clone();
+    let reload_sink = tomotroid.audio_sink.clone();
+    let reload_hotkeys = tomotroid.hotkeys.clone();
+    let reload_timer = Timer::default();
+    tomotroid.window.on_settings_file_changed(move || {
+        let reload_handle = reload_handle.clone();
+        let reload_model = reload_model.clone();
+        let reload_sink = reload_sink.clone();
+        let reload_hotkeys = reload_hotkeys.clone();
+        reload_timer.start(
+            TimerMode::SingleShot,
+            std::time::Duration::from_millis(250),
+            move || {
+                let Some(main) = reload_handle.upgrade() else {
+                    return;
+                };
+                if let Some(name) = settings::take_profile_request() {
+                    let mut settings = main.json_settings();
+                    match settings::find_profile(&settings, &name) {
+                        Ok(idx) => {
+                            settings.switch_profile(idx);
+                            info!("Switching to the {name} profile, as asked");
+                            main.apply_settings(
+                                &settings,
+                                &reload_model,
+                                &reload_sink,
+                                &reload_hotkeys,
+                            );
+                            main.save_settings();
+                        }
+                        Err(e) => warn!("{e}"),
+                    }
+                }
+                match settings::reload_settings() {
+                    Ok(Some(settings)) => {
+                        info!("The settings file changed, applying it");
+                        main.apply_settings(
+                            &settings,
+                            &reload_model,
+                            &reload_sink,
+                            &reload_hotkeys,
+                        );
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!("Unable to reload the changed settings file: {e}");
+                        let notice = format!("The settings file changed but couldn't be read: {e}");
+                        main.set_notice(notice.into());
+                    }
+                }
+            },
+        );
+    });
+
+    let result = tomotroid.run();
+
+    //anything still waiting on the save delay needs to be written before exiting
+    if let Err(e) = settings::flush_settings() {
+        error!("Unable to save the settings on exit: {e}");
+    }
+
+    drop(ghk_stop_tx);
+    if ghk_thread.join().is_err() {
+        error!("Global hotkey thread panicked");
+    }
+
+    result?;
     Ok(())
 }
@@ -0,0 +1,109 @@
+use crate::{BreakOverlay, Main, Settings, Theme};
+use i_slint_backend_winit::{winit::window::Fullscreen, WinitWindowAccessor};
+use log::{error, info};
+use slint::ComponentHandle;
+use std::cell::RefCell;
+
+//Only ever touched from the event loop
+thread_local! {
+    static OVERLAYS: RefCell<Vec<BreakOverlay>> = const { RefCell::new(Vec::new()) };
+}
+
+//Puts the overlays up for a strict break, keeps them in step with the timer and takes them down
+//again once it's over. Called on every tick and whenever the timer changes phase or state.
+pub fn sync(main: &Main) {
+    let wanted = main.global::<Settings>().get_strict_breaks() && !main.get_phase().focus;
+    OVERLAYS.with_borrow_mut(|overlays| {
+        if !wanted {
+            for overlay in overlays.drain(..) {
+                if let Err(e) = overlay.hide() {
+                    error!("Unable to close the break overlay: {e}");
+                }
+            }
+            return;
+        }
+
+        if overlays.is_empty() {
+            *overlays = open(main);
+        }
+        for overlay in overlays.iter() {
+            update(main, overlay);
+        }
+    });
+}
+
+//One per monitor. The main window can't list them while it's hidden away in the tray, in which
+//case a single overlay goes wherever the system puts it.
+fn open(main: &Main) -> Vec<BreakOverlay> {
+    let monitors: Vec<_> = main
+        .window()
+        .with_winit_window(|window| window.available_monitors().map(Some).collect())
+        .unwrap_or_default();
+    let monitors = if monitors.is_empty() {
+        vec![None]
+    } else {
+        monitors
+    };
+    info!("Covering {} screen(s) for the break", monitors.len());
+
+    monitors
+        .into_iter()
+        .filter_map(|monitor| {
+            let overlay = BreakOverlay::new()
+                .map_err(|e| error!("Unable to create the break overlay: {e}"))
+                .ok()?;
+            copy_theme(main, &overlay);
+
+            //The skip ends up closing this overlay, which can't happen while it's still handling
+            //the click, so the skip waits for the event loop to come back around
+            let skip_handle = main.as_weak();
+            overlay.on_skip(move |held| {
+                let _ = skip_handle.upgrade_in_event_loop(move |main| main.skip_break(held));
+            });
+            //Answering on one screen takes the prompt off all of them, along with the main window's
+            let reflect_handle = main.as_weak();
+            overlay.on_reflect(move |rating, note| {
+                let _ = reflect_handle.upgrade_in_event_loop(move |main| {
+                    main.invoke_reflect(rating, note);
+                    sync(&main);
+                });
+            });
+
+            if let Err(e) = overlay.show() {
+                error!("Unable to show the break overlay: {e}");
+                return None;
+            }
+            overlay.window().with_winit_window(|window| {
+                window.set_fullscreen(Some(Fullscreen::Borderless(monitor)));
+                window.focus_window();
+            });
+            Some(overlay)
+        })
+        .collect()
+}
+
+fn update(main: &Main, overlay: &BreakOverlay) {
+    overlay.set_phase_label(main.get_phase_label());
+    overlay.set_remaining(main.invoke_time_remaining());
+    overlay.set_progress(main.get_progress());
+    overlay.set_phase_color(main.get_phase_color());
+    overlay.set_activity(main.get_activity());
+    overlay.set_skips_left(main.skips_left());
+    overlay.set_skip_in(main.skip_in());
+    overlay.set_reflecting(main.get_reflecting());
+}
+
+fn copy_theme(main: &Main, overlay: &BreakOverlay) {
+    let from = main.global::<Theme>();
+    let to = overlay.global::<Theme>();
+    to.set_long_round(from.get_long_round());
+    to.set_short_round(from.get_short_round());
+    to.set_focus_round(from.get_focus_round());
+    to.set_background(from.get_background());
+    to.set_background_light(from.get_background_light());
+    to.set_background_lightest(from.get_background_lightest());
+    to.set_foreground(from.get_foreground());
+    to.set_foreground_darker(from.get_foreground_darker());
+    to.set_foreground_darkest(from.get_foreground_darkest());
+    to.set_accent(from.get_accent());
+}
@@ -0,0 +1,255 @@
+use crate::settings::{JsonWeekday, JsonWorkHours};
+use chrono::{Datelike, Local, NaiveDateTime, NaiveTime, TimeDelta, Weekday};
+use std::fmt;
+use std::time::Duration;
+
+//How often to look for the start of the working hours. Starting half a minute late doesn't matter.
+pub const CHECK: Duration = Duration::from_secs(30);
+
+const DAYS: [(JsonWeekday, &str); 7] = [
+    (JsonWeekday::Mon, "Mon"),
+    (JsonWeekday::Tue, "Tue"),
+    (JsonWeekday::Wed, "Wed"),
+    (JsonWeekday::Thu, "Thu"),
+    (JsonWeekday::Fri, "Fri"),
+    (JsonWeekday::Sat, "Sat"),
+    (JsonWeekday::Sun, "Sun"),
+];
+
+impl From<JsonWeekday> for Weekday {
+    fn from(day: JsonWeekday) -> Self {
+        match day {
+            JsonWeekday::Mon => Weekday::Mon,
+            JsonWeekday::Tue => Weekday::Tue,
+            JsonWeekday::Wed => Weekday::Wed,
+            JsonWeekday::Thu => Weekday::Thu,
+            JsonWeekday::Fri => Weekday::Fri,
+            JsonWeekday::Sat => Weekday::Sat,
+            JsonWeekday::Sun => Weekday::Sun,
+        }
+    }
+}
+
+fn day_index(day: JsonWeekday) -> usize {
+    DAYS.iter().position(|(d, _)| *d == day).unwrap_or(0)
+}
+
+//Full names work too, only the first three letters are looked at
+fn parse_day(text: &str) -> Option<JsonWeekday> {
+    let text = text.trim().to_lowercase();
+    DAYS.iter()
+        .find(|(_, name)| text.get(..3) == Some(&name.to_lowercase()))
+        .map(|(day, _)| *day)
+}
+
+fn parse_time(text: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(text.trim(), "%H:%M").ok()
+}
+
+impl JsonWorkHours {
+    //Reads hours typed like "Mon-Fri 09:00-12:00" or "Mon, Wed, Sat 13:00-17:30". Day ranges can
+    //wrap around the weekend, like "Sat-Mon". The hours can't run past midnight. Several ranges of
+    //hours for the same days, like "Mon-Fri 09:00-12:00, 13:00-17:30", come back as one each.
+    #[must_use]
+    pub fn parse(text: &str) -> Option<Vec<Self>> {
+        let text = text.replace('–', "-");
+        let split = text.find(|c: char| c.is_ascii_digit())?;
+        let (days_text, times_text) = text.split_at(split);
+
+        let mut days = Vec::new();
+        for part in days_text.split(',').filter(|part| !part.trim().is_empty()) {
+            let (first, last) = match part.split_once('-') {
+                Some((first, last)) => (parse_day(first)?, parse_day(last)?),
+                None => (parse_day(part)?, parse_day(part)?),
+            };
+            let (first, last) = (day_index(first), day_index(last));
+            let count = (last + 7 - first) % 7 + 1;
+            for idx in (first..first + count).map(|idx| idx % 7) {
+                if !days.contains(&DAYS[idx].0) {
+                    days.push(DAYS[idx].0);
+                }
+            }
+        }
+        if days.is_empty() {
+            return None;
+        }
+        days.sort_by_key(|day| day_index(*day));
+
+        times_text
+            .split(',')
+            .map(|range| {
+                let (start, end) = range.split_once('-')?;
+                let (start, end) = (parse_time(start)?, parse_time(end)?);
+                (start < end).then(|| Self {
+                    days: days.clone(),
+                    start: start.format("%H:%M").to_string(),
+                    end: end.format("%H:%M").to_string(),
+                })
+            })
+            .collect()
+    }
+
+    //The end of these hours if now falls inside them
+    fn ends(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let (start, end) = (parse_time(&self.start)?, parse_time(&self.end)?);
+        let today = now.weekday();
+        if !self.days.iter().any(|day| Weekday::from(*day) == today) {
+            return None;
+        }
+        (start <= now.time() && now.time() < end).then(|| now.date().and_time(end))
+    }
+}
+
+//Runs of days next to each other are shortened, so it reads back the way it was most likely typed
+impl fmt::Display for JsonWorkHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut idxs: Vec<usize> = self.days.iter().map(|day| day_index(*day)).collect();
+        idxs.sort_unstable();
+        idxs.dedup();
+
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for idx in idxs {
+            match runs.last_mut() {
+                Some((_, last)) if *last + 1 == idx => *last = idx,
+                _ => runs.push((idx, idx)),
+            }
+        }
+        let days: Vec<String> = runs
+            .iter()
+            .map(|&(first, last)| match last - first {
+                0 => DAYS[first].1.to_string(),
+                1 => format!("{}, {}", DAYS[first].1, DAYS[last].1),
+                _ => format!("{}-{}", DAYS[first].1, DAYS[last].1),
+            })
+            .collect();
+
+        write!(f, "{} {}-{}", days.join(", "), self.start, self.end)
+    }
+}
+
+//The latest end of any working hours going on at that time
+fn window_end(hours: &[JsonWorkHours], now: NaiveDateTime) -> Option<NaiveDateTime> {
+    hours.iter().filter_map(|hours| hours.ends(now)).max()
+}
+
+#[must_use]
+pub fn working_now(hours: &[JsonWorkHours]) -> bool {
+    window_end(hours, Local::now().naive_local()).is_some()
+}
+
+//Whether something that takes this many seconds can be started on its own right now. Without any
+//working hours set it always can.
+#[must_use]
+pub fn allows(hours: &[JsonWorkHours], seconds: i64) -> bool {
+    allows_at(hours, seconds, Local::now().naive_local())
+}
+
+fn allows_at(hours: &[JsonWorkHours], seconds: i64, now: NaiveDateTime) -> bool {
+    if hours.is_empty() {
+        return true;
+    }
+    window_end(hours, now).is_some_and(|end| now + TimeDelta::seconds(seconds) <= end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn one(text: &str) -> JsonWorkHours {
+        let mut hours = JsonWorkHours::parse(text).unwrap();
+        assert_eq!(hours.len(), 1);
+        hours.remove(0)
+    }
+
+    //2025-06-02 was a Monday
+    fn monday_at(time: &str) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 6, 2)
+            .unwrap()
+            .and_time(parse_time(time).unwrap())
+    }
+
+    #[test]
+    fn parses_days_and_hours() {
+        let hours = one("Mon-Fri 09:00-17:00");
+        assert_eq!(
+            hours.days,
+            vec![
+                JsonWeekday::Mon,
+                JsonWeekday::Tue,
+                JsonWeekday::Wed,
+                JsonWeekday::Thu,
+                JsonWeekday::Fri
+            ]
+        );
+        assert_eq!(
+            (hours.start.as_str(), hours.end.as_str()),
+            ("09:00", "17:00")
+        );
+
+        assert_eq!(
+            one("sunday, wed 8:30-9:00").to_string(),
+            "Wed, Sun 08:30-09:00"
+        );
+        assert_eq!(
+            one("Sat-Mon 10:00-11:00").to_string(),
+            "Mon, Sat, Sun 10:00-11:00"
+        );
+    }
+
+    #[test]
+    fn parses_several_ranges_of_hours() {
+        let hours = JsonWorkHours::parse("Mon–Fri 09:00–12:00, 13:00–17:30").unwrap();
+        let text: Vec<String> = hours.iter().map(ToString::to_string).collect();
+        assert_eq!(text, ["Mon-Fri 09:00-12:00", "Mon-Fri 13:00-17:30"]);
+    }
+
+    #[test]
+    fn rejects_bad_hours() {
+        for text in [
+            "",
+            "09:00-17:00",
+            "Mon-Fri",
+            "Xyz 09:00-17:00",
+            "Mon 17:00-09:00",
+            "Mon 09:00-09:00",
+            "Mon 09:00",
+            "Mon 09:00-12:00, 25:00-26:00",
+        ] {
+            assert_eq!(JsonWorkHours::parse(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn display_reads_back_the_same() {
+        for text in [
+            "Mon-Fri 09:00-17:00",
+            "Mon, Wed, Sat 13:00-17:30",
+            "Mon, Tue 07:15-08:45",
+            "Sun 10:00-11:00",
+        ] {
+            let hours = one(text);
+            assert_eq!(hours.to_string(), text);
+            assert_eq!(one(&hours.to_string()), hours);
+        }
+    }
+
+    #[test]
+    fn allows_up_to_the_end_of_the_window() {
+        let hours = JsonWorkHours::parse("Mon-Fri 09:00-12:00, 13:00-17:30").unwrap();
+        assert!(allows_at(&hours, 25 * 60, monday_at("09:00")));
+        assert!(allows_at(&hours, 25 * 60, monday_at("11:35")));
+        assert!(!allows_at(&hours, 25 * 60, monday_at("11:36")));
+        assert!(!allows_at(&hours, 0, monday_at("12:00")));
+        assert!(!allows_at(&hours, 0, monday_at("08:59")));
+        assert!(allows_at(&hours, 0, monday_at("13:00")));
+        assert!(!allows_at(&hours, 60, monday_at("17:30")));
+        let saturday = monday_at("10:00") + TimeDelta::days(5);
+        assert!(!allows_at(&hours, 60, saturday));
+    }
+
+    #[test]
+    fn no_hours_allow_anything() {
+        assert!(allows_at(&[], 60 * 60, monday_at("03:00")));
+    }
+}
@@ -1,19 +1,27 @@
-use crate::{BoolSettTypes, ConfigData, IntSettTypes, JsonTheme, Main, Settings, Theme};
+use crate::{
+    BoolSettTypes, ConfigData, IntSettTypes, JsonTheme, LockAction, Main, MeetingAction, Phase,
+    PhaseColor, PhaseSound, Profile, Settings, Theme,
+};
 use core::fmt;
 use directories::ProjectDirs;
 use global_hotkey::hotkey::{Code, HotKey, Modifiers};
 use hex_color::HexColor;
+use log::{error, info, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use rodio::Sink;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use slint::{platform::Key, Color, ComponentHandle, Model, SharedString, Timer, VecModel, Weak};
 use std::{
+    cell::RefCell,
     env,
-    fs::{File, OpenOptions},
-    io::{BufReader, BufWriter},
-    path::Path,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
     rc::Rc,
     str::FromStr,
     sync::OnceLock,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use walkdir::WalkDir;
 
@@ -97,7 +105,10 @@ impl fmt::Display for GKeyCode {
                 Code::Quote => "\"",
                 Code::Semicolon => ";",
                 Code::Slash => "/",
-                Code::Space => " ",
+                Code::ArrowUp => "Up",
+                Code::ArrowDown => "Down",
+                Code::ArrowLeft => "Left",
+                Code::ArrowRight => "Right",
                 Code::NumpadAdd => "Add",
                 Code::NumpadClear => "Clear",
                 Code::NumpadDivide => "Divide",
@@ -169,6 +180,10 @@ impl std::str::FromStr for GKeyCode {
             ";" => Code::Semicolon,
             "/" => Code::Slash,
             " " => Code::Space,
+            "Up" => Code::ArrowUp,
+            "Down" => Code::ArrowDown,
+            "Left" => Code::ArrowLeft,
+            "Right" => Code::ArrowRight,
             "Add" => Code::NumpadAdd,
             "Clear" => Code::NumpadClear,
             "Divide" => Code::NumpadDivide,
@@ -353,12 +368,12 @@ impl std::str::FromStr for JsonHotKey {
                     "Alt" => mods.set(Modifiers::ALT, true),
                     "Shift" => mods.set(Modifiers::SHIFT, true),
                     "Super" => mods.set(Modifiers::META, true),
-                    _ => panic!("No Other modifier keys currently supported"),
-                };
+                    _ => return Err("No Other modifier keys currently supported"),
+                }
             } else {
                 return Ok(JsonHotKey {
                     modifiers: mods,
-                    key: key.parse().unwrap(),
+                    key: key.parse()?,
                 });
             }
         }
@@ -419,22 +434,479 @@ impl Into<HotKey> for &JsonHotKey {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct JsonSettings {
+    //Pomotroid never had this, so a file without it is treated as version 0
+    pub version: u32,
     pub always_on_top: bool,
     pub auto_start_break_timer: bool,
     pub auto_start_work_timer: bool,
     pub break_always_on_top: bool,
     pub global_shortcuts: GlobalShortcuts,
+    pub local_shortcuts: LocalShortcuts,
     pub min_to_tray: bool,
     pub min_to_tray_on_close: bool,
     pub notifications: bool,
     pub theme: String,
     pub tick_sounds: bool,
     pub tick_sounds_during_break: bool,
+    //durations are in seconds since version 3, Pomotroid (and versions before that) used minutes
     pub time_long_break: i32,
     pub time_short_break: i32,
     pub time_work: i32,
     pub volume: i32,
     pub work_rounds: i32,
+    pub profiles: Vec<JsonProfile>,
+    pub active_profile: String,
+    #[serde(deserialize_with = "valid_phases")]
+    pub phase_sequence: Vec<JsonPhase>,
+    pub overtime: bool,
+    pub snooze_limit: i32,
+    //seconds added by the extend action
+    pub extend_by: i32,
+    //The daily goal, either focus rounds to get through or minutes of focus depending on
+    //goal_in_minutes. The rounds are separate from work_rounds, which is how many come before
+    //each long break. 0 means there's no goal.
+    pub daily_rounds: i32,
+    pub daily_minutes: i32,
+    pub goal_in_minutes: bool,
+    pub stop_at_target: bool,
+    //the hour the day turns over for the goal
+    pub day_start: i32,
+    //seconds without any input before a running focus pauses itself, 0 turns it off
+    pub idle_threshold: i32,
+    //what happens to a running timer when the screen locks or the computer goes to sleep
+    pub lock_action: JsonLockAction,
+    //Fullscreen breaks on every monitor. They can be skipped by holding escape, or with a click
+    //once strict_skip_after seconds have passed (0 leaves only holding), but only so many a day.
+    pub strict_breaks: bool,
+    pub strict_skip_after: i32,
+    pub strict_skip_limit: i32,
+    //Seconds of focus between little reminders to rest the eyes or check posture, which don't
+    //end the phase. 0 turns them off. Part of the profile.
+    pub micro_break_every: i32,
+    //When to work. With any set, focus only starts on its own inside them, and not if it would
+    //run past the end of one. Empty means any time.
+    pub work_hours: Vec<JsonWorkHours>,
+    //paths to .ics files, checked for meetings that get in the way of focus
+    pub calendars: Vec<String>,
+    pub meeting_action: JsonMeetingAction,
+    //ask for a rating and a note on the break screen after each focus round
+    pub reflection: bool,
+}
+
+//One step of a custom sequence, like "focus 50, break 10, focus 50, break 30, review 15". When the
+//sequence is empty the classic focus/short break/long break cycle is built from the timer settings.
+//Whether a phase counts as focus decides which auto-start setting and tick sounds apply to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonPhase {
+    pub name: String,
+    pub seconds: i32,
+    #[serde(default)]
+    pub color: JsonPhaseColor,
+    #[serde(default)]
+    pub sound: JsonPhaseSound,
+    #[serde(default)]
+    pub focus: bool,
+}
+
+impl JsonPhase {
+    //Reads a step typed like "Focus 50:00" or "Long Break 30m", the name followed by anything
+    //parse_duration takes. Editing a step keeps its colour, sound and whether it's focus as long as
+    //the name stays the same, otherwise they're worked out from the name.
+    #[must_use]
+    pub fn parse(text: &str, previous: Option<&JsonPhase>) -> Option<Self> {
+        let (name, time) = text.trim().rsplit_once(char::is_whitespace)?;
+        let (name, seconds) = (name.trim(), parse_duration(time)?);
+        if name.is_empty() {
+            return None;
+        }
+        if let Some(previous) = previous.filter(|phase| phase.name.eq_ignore_ascii_case(name)) {
+            return Some(JsonPhase {
+                name: name.to_string(),
+                seconds,
+                ..previous.clone()
+            });
+        }
+
+        let lower = name.to_lowercase();
+        let (color, sound, focus) = if !lower.contains("break") && !lower.contains("rest") {
+            (JsonPhaseColor::FocusRound, JsonPhaseSound::Work, true)
+        } else if lower.contains("long") {
+            (JsonPhaseColor::LongRound, JsonPhaseSound::LongBreak, false)
+        } else {
+            (
+                JsonPhaseColor::ShortRound,
+                JsonPhaseSound::ShortBreak,
+                false,
+            )
+        };
+        Some(JsonPhase {
+            name: name.to_string(),
+            seconds,
+            color,
+            sound,
+            focus,
+        })
+    }
+}
+
+//A step needs at least a second, otherwise the timer would go straight through it on every tick
+fn valid_phases<'de, D>(deserializer: D) -> Result<Vec<JsonPhase>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut phases = Vec::<JsonPhase>::deserialize(deserializer)?;
+    phases.retain(|phase| {
+        if phase.seconds < 1 {
+            warn!("Leaving out the {} step, it has no time", phase.name);
+        }
+        phase.seconds >= 1
+    });
+    Ok(phases)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonWeekday {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+//One stretch of working hours on the given days, like Monday to Friday from 09:00 until 12:00.
+//The times are written as "HH:MM" so the file stays easy to edit by hand.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonWorkHours {
+    pub days: Vec<JsonWeekday>,
+    pub start: String,
+    pub end: String,
+}
+
+//the theme colour the phase is drawn in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JsonPhaseColor {
+    #[default]
+    FocusRound,
+    ShortRound,
+    LongRound,
+    Accent,
+}
+
+//the alert played when the phase starts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JsonPhaseSound {
+    #[default]
+    Work,
+    ShortBreak,
+    LongBreak,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JsonLockAction {
+    #[default]
+    Pause,
+    KeepRunning,
+    //a focus skips ahead to its break, and the time away counts towards it
+    TakeBreak,
+}
+
+//what a running timer does while a meeting is on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JsonMeetingAction {
+    #[default]
+    Pause,
+    //the meeting takes the place of the break, so the focus it cuts into ends and the break
+    //after it is skipped once it's over
+    Skip,
+    KeepRunning,
+}
+
+impl From<JsonMeetingAction> for MeetingAction {
+    fn from(action: JsonMeetingAction) -> Self {
+        match action {
+            JsonMeetingAction::Pause => MeetingAction::Pause,
+            JsonMeetingAction::Skip => MeetingAction::Skip,
+            JsonMeetingAction::KeepRunning => MeetingAction::KeepRunning,
+        }
+    }
+}
+
+impl From<MeetingAction> for JsonMeetingAction {
+    fn from(action: MeetingAction) -> Self {
+        match action {
+            MeetingAction::Pause => JsonMeetingAction::Pause,
+            MeetingAction::Skip => JsonMeetingAction::Skip,
+            MeetingAction::KeepRunning => JsonMeetingAction::KeepRunning,
+        }
+    }
+}
+
+impl From<JsonLockAction> for LockAction {
+    fn from(action: JsonLockAction) -> Self {
+        match action {
+            JsonLockAction::Pause => LockAction::Pause,
+            JsonLockAction::KeepRunning => LockAction::KeepRunning,
+            JsonLockAction::TakeBreak => LockAction::TakeBreak,
+        }
+    }
+}
+
+impl From<LockAction> for JsonLockAction {
+    fn from(action: LockAction) -> Self {
+        match action {
+            LockAction::Pause => JsonLockAction::Pause,
+            LockAction::KeepRunning => JsonLockAction::KeepRunning,
+            LockAction::TakeBreak => JsonLockAction::TakeBreak,
+        }
+    }
+}
+
+impl From<JsonPhaseColor> for PhaseColor {
+    fn from(color: JsonPhaseColor) -> Self {
+        match color {
+            JsonPhaseColor::FocusRound => PhaseColor::FocusRound,
+            JsonPhaseColor::ShortRound => PhaseColor::ShortRound,
+            JsonPhaseColor::LongRound => PhaseColor::LongRound,
+            JsonPhaseColor::Accent => PhaseColor::Accent,
+        }
+    }
+}
+
+impl From<PhaseColor> for JsonPhaseColor {
+    fn from(color: PhaseColor) -> Self {
+        match color {
+            PhaseColor::FocusRound => JsonPhaseColor::FocusRound,
+            PhaseColor::ShortRound => JsonPhaseColor::ShortRound,
+            PhaseColor::LongRound => JsonPhaseColor::LongRound,
+            PhaseColor::Accent => JsonPhaseColor::Accent,
+        }
+    }
+}
+
+impl From<JsonPhaseSound> for PhaseSound {
+    fn from(sound: JsonPhaseSound) -> Self {
+        match sound {
+            JsonPhaseSound::Work => PhaseSound::Work,
+            JsonPhaseSound::ShortBreak => PhaseSound::ShortBreak,
+            JsonPhaseSound::LongBreak => PhaseSound::LongBreak,
+            JsonPhaseSound::None => PhaseSound::None,
+        }
+    }
+}
+
+impl From<PhaseSound> for JsonPhaseSound {
+    fn from(sound: PhaseSound) -> Self {
+        match sound {
+            PhaseSound::Work => JsonPhaseSound::Work,
+            PhaseSound::ShortBreak => JsonPhaseSound::ShortBreak,
+            PhaseSound::LongBreak => JsonPhaseSound::LongBreak,
+            PhaseSound::None => JsonPhaseSound::None,
+        }
+    }
+}
+
+impl From<&JsonPhase> for Phase {
+    fn from(phase: &JsonPhase) -> Self {
+        Phase {
+            name: (&phase.name).into(),
+            seconds: phase.seconds,
+            color: phase.color.into(),
+            sound: phase.sound.into(),
+            focus: phase.focus,
+        }
+    }
+}
+
+impl From<&Phase> for JsonPhase {
+    fn from(phase: &Phase) -> Self {
+        JsonPhase {
+            name: phase.name.to_string(),
+            seconds: phase.seconds,
+            color: phase.color.into(),
+            sound: phase.sound.into(),
+            focus: phase.focus,
+        }
+    }
+}
+
+//A named set of timer settings that can be switched between. The values of the active one live in
+//the top level fields like they always have (and that's all Pomotroid knows about), the copy in the
+//list is updated from them whenever the settings are saved.
+//Anything left out of a hand written profile uses the Classic 25/5 values.
+#[allow(clippy::struct_excessive_bools)] //same bools as the top level settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct JsonProfile {
+    pub name: String,
+    pub time_work: i32,
+    pub time_short_break: i32,
+    pub time_long_break: i32,
+    pub work_rounds: i32,
+    pub auto_start_work_timer: bool,
+    pub auto_start_break_timer: bool,
+    pub tick_sounds: bool,
+    pub tick_sounds_during_break: bool,
+    pub micro_break_every: i32,
+}
+
+impl Default for JsonProfile {
+    fn default() -> Self {
+        JsonProfile {
+            name: "Classic 25/5".to_string(),
+            time_work: 25 * 60,
+            time_short_break: 5 * 60,
+            time_long_break: 15 * 60,
+            work_rounds: 4,
+            auto_start_work_timer: true,
+            auto_start_break_timer: true,
+            tick_sounds: false,
+            tick_sounds_during_break: true,
+            micro_break_every: 0,
+        }
+    }
+}
+
+impl From<&JsonProfile> for Profile {
+    fn from(profile: &JsonProfile) -> Self {
+        Profile {
+            name: (&profile.name).into(),
+            time_work: profile.time_work,
+            time_short_break: profile.time_short_break,
+            time_long_break: profile.time_long_break,
+            work_rounds: profile.work_rounds,
+            auto_start_work_timer: profile.auto_start_work_timer,
+            auto_start_break_timer: profile.auto_start_break_timer,
+            tick_sounds: profile.tick_sounds,
+            tick_sounds_during_break: profile.tick_sounds_during_break,
+            micro_break_every: profile.micro_break_every,
+        }
+    }
+}
+
+impl From<&Profile> for JsonProfile {
+    fn from(profile: &Profile) -> Self {
+        JsonProfile {
+            name: profile.name.to_string(),
+            time_work: profile.time_work,
+            time_short_break: profile.time_short_break,
+            time_long_break: profile.time_long_break,
+            work_rounds: profile.work_rounds,
+            auto_start_work_timer: profile.auto_start_work_timer,
+            auto_start_break_timer: profile.auto_start_break_timer,
+            tick_sounds: profile.tick_sounds,
+            tick_sounds_during_break: profile.tick_sounds_during_break,
+            micro_break_every: profile.micro_break_every,
+        }
+    }
+}
+
+impl JsonSettings {
+    //The custom sequence if there is one, otherwise the classic cycle of focus and short breaks
+    //with a long break after the last round
+    #[must_use]
+    pub fn phases(&self) -> Vec<JsonPhase> {
+        if !self.phase_sequence.is_empty() {
+            return self.phase_sequence.clone();
+        }
+
+        let focus = JsonPhase {
+            name: "Focus".to_string(),
+            seconds: self.time_work,
+            color: JsonPhaseColor::FocusRound,
+            sound: JsonPhaseSound::Work,
+            focus: true,
+        };
+        let short_break = JsonPhase {
+            name: "Short Break".to_string(),
+            seconds: self.time_short_break,
+            color: JsonPhaseColor::ShortRound,
+            sound: JsonPhaseSound::ShortBreak,
+            focus: false,
+        };
+        let long_break = JsonPhase {
+            name: "Long Break".to_string(),
+            seconds: self.time_long_break,
+            color: JsonPhaseColor::LongRound,
+            sound: JsonPhaseSound::LongBreak,
+            focus: false,
+        };
+
+        let mut phases = Vec::new();
+        for _ in 1..self.work_rounds {
+            phases.push(focus.clone());
+            phases.push(short_break.clone());
+        }
+        phases.push(focus);
+        phases.push(long_break);
+        phases
+    }
+
+    //Copies the current timer settings into the active profile, adding it if it isn't in the list
+    pub fn store_profile(&mut self) {
+        let current = JsonProfile {
+            name: self.active_profile.clone(),
+            time_work: self.time_work,
+            time_short_break: self.time_short_break,
+            time_long_break: self.time_long_break,
+            work_rounds: self.work_rounds,
+            auto_start_work_timer: self.auto_start_work_timer,
+            auto_start_break_timer: self.auto_start_break_timer,
+            tick_sounds: self.tick_sounds,
+            tick_sounds_during_break: self.tick_sounds_during_break,
+            micro_break_every: self.micro_break_every,
+        };
+        match self
+            .profiles
+            .iter_mut()
+            .find(|profile| profile.name == self.active_profile)
+        {
+            Some(profile) => *profile = current,
+            None => self.profiles.push(current),
+        }
+    }
+
+    //Keeps any changes to the current profile, then makes the one at idx active
+    pub fn switch_profile(&mut self, idx: usize) {
+        self.store_profile();
+        let Some(profile) = self.profiles.get(idx).cloned() else {
+            return;
+        };
+        self.time_work = profile.time_work;
+        self.time_short_break = profile.time_short_break;
+        self.time_long_break = profile.time_long_break;
+        self.work_rounds = profile.work_rounds;
+        self.auto_start_work_timer = profile.auto_start_work_timer;
+        self.auto_start_break_timer = profile.auto_start_break_timer;
+        self.tick_sounds = profile.tick_sounds;
+        self.tick_sounds_during_break = profile.tick_sounds_during_break;
+        self.micro_break_every = profile.micro_break_every;
+        self.active_profile = profile.name;
+    }
+
+    //The profile before or after the active one, wrapping around at either end
+    #[must_use]
+    pub fn next_profile(&self, forward: bool) -> usize {
+        let count = self.profiles.len().max(1);
+        let current = self
+            .profiles
+            .iter()
+            .position(|profile| profile.name == self.active_profile)
+            .unwrap_or(0);
+        if forward {
+            (current + 1) % count
+        } else {
+            (current + count - 1) % count
+        }
+    }
 }
 
 //Need to look into if the serialization of the Slint structs in better in the newer release
@@ -473,8 +945,162 @@ pub struct GlobalShortcuts {
     pub skip: JsonHotKey,
     #[serde(rename = "call-timer-toggle")]
     pub toggle: JsonHotKey,
+    #[serde(rename = "call-timer-snooze")]
+    pub snooze: JsonHotKey,
+    #[serde(rename = "call-timer-extend")]
+    pub extend: JsonHotKey,
+}
+
+//These are only active while the window has focus, so unlike the global shortcuts they don't need
+//a modifier and still work under Wayland where the global ones are disabled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocalShortcuts {
+    #[serde(rename = "timer-toggle")]
+    pub toggle: JsonHotKey,
+    #[serde(rename = "timer-reset")]
+    pub reset: JsonHotKey,
+    #[serde(rename = "timer-skip")]
+    pub skip: JsonHotKey,
+    #[serde(rename = "timer-snooze")]
+    pub snooze: JsonHotKey,
+    #[serde(rename = "timer-extend")]
+    pub extend: JsonHotKey,
+    #[serde(rename = "volume-mute")]
+    pub mute: JsonHotKey,
+    #[serde(rename = "volume-up")]
+    pub volume_up: JsonHotKey,
+    #[serde(rename = "volume-down")]
+    pub volume_down: JsonHotKey,
+    #[serde(rename = "tab-timer")]
+    pub tab_timer: JsonHotKey,
+    #[serde(rename = "tab-options")]
+    pub tab_options: JsonHotKey,
+    #[serde(rename = "tab-themes")]
+    pub tab_themes: JsonHotKey,
+    #[serde(rename = "tab-about")]
+    pub tab_about: JsonHotKey,
+}
+
+impl Default for LocalShortcuts {
+    fn default() -> Self {
+        let key = |code: Code| JsonHotKey {
+            modifiers: Modifiers::empty(),
+            key: code.into(),
+        };
+
+        LocalShortcuts {
+            toggle: key(Code::Space),
+            reset: key(Code::KeyR),
+            skip: key(Code::KeyS),
+            snooze: key(Code::KeyZ),
+            extend: key(Code::KeyE),
+            mute: key(Code::KeyM),
+            volume_up: key(Code::ArrowUp),
+            volume_down: key(Code::ArrowDown),
+            tab_timer: key(Code::Digit1),
+            tab_options: key(Code::Digit2),
+            tab_themes: key(Code::Digit3),
+            tab_about: key(Code::Digit4),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LocalAction {
+    Toggle,
+    Reset,
+    Skip,
+    Snooze,
+    Extend,
+    Mute,
+    VolumeUp,
+    VolumeDown,
+    Tab(i32),
+}
+
+impl LocalShortcuts {
+    #[must_use]
+    pub fn action(&self, pressed: &JsonHotKey) -> Option<LocalAction> {
+        [
+            (&self.toggle, LocalAction::Toggle),
+            (&self.reset, LocalAction::Reset),
+            (&self.skip, LocalAction::Skip),
+            (&self.snooze, LocalAction::Snooze),
+            (&self.extend, LocalAction::Extend),
+            (&self.mute, LocalAction::Mute),
+            (&self.volume_up, LocalAction::VolumeUp),
+            (&self.volume_down, LocalAction::VolumeDown),
+            (&self.tab_timer, LocalAction::Tab(0)),
+            (&self.tab_options, LocalAction::Tab(1)),
+            (&self.tab_themes, LocalAction::Tab(2)),
+            (&self.tab_about, LocalAction::Tab(3)),
+        ]
+        .into_iter()
+        .find_map(|(key, action)| (key == pressed).then_some(action))
+    }
 }
 
+const PREFS_FILE: &str = "preferences.json";
+//written by a second Tomotroid started with --profile for the running one to pick up
+const PROFILE_REQUEST: &str = "profile-request";
+
+//Each entry upgrades the settings by a single version, so MIGRATIONS[n] takes a version n file to n + 1.
+//They only need to handle renamed or reshaped fields, anything that's simply missing gets filled in
+//from default-preferences.json after the migrations have run.
+type Migration = fn(&mut Map<String, Value>);
+const MIGRATIONS: &[Migration] = &[
+    //0 -> 1: the original Pomotroid format, nothing changes other than gaining a version number
+    |_| {},
+    //1 -> 2: timer profiles, whatever the timer was set to becomes the first one
+    |fields| {
+        let mut profile: Map<String, Value> = [
+            "timeWork",
+            "timeShortBreak",
+            "timeLongBreak",
+            "workRounds",
+            "autoStartWorkTimer",
+            "autoStartBreakTimer",
+            "tickSounds",
+            "tickSoundsDuringBreak",
+        ]
+        .into_iter()
+        .filter_map(|key| fields.get(key).map(|val| (key.to_string(), val.clone())))
+        .collect();
+        profile.insert("name".to_string(), Value::from("Default"));
+        fields.insert(
+            "profiles".to_string(),
+            Value::Array(vec![Value::Object(profile)]),
+        );
+        fields.insert("activeProfile".to_string(), Value::from("Default"));
+    },
+    //2 -> 3: durations go from whole minutes to seconds
+    |fields| {
+        fn to_seconds(fields: &mut Map<String, Value>, from: &str, to: &str) {
+            if let Some(minutes) = fields.remove(from).and_then(|minutes| minutes.as_i64()) {
+                fields.insert(to.to_string(), Value::from(minutes * 60));
+            }
+        }
+
+        for key in ["timeWork", "timeShortBreak", "timeLongBreak"] {
+            to_seconds(fields, key, key);
+        }
+        if let Some(Value::Array(profiles)) = fields.get_mut("profiles") {
+            for profile in profiles.iter_mut().filter_map(Value::as_object_mut) {
+                for key in ["timeWork", "timeShortBreak", "timeLongBreak"] {
+                    to_seconds(profile, key, key);
+                }
+            }
+        }
+        if let Some(Value::Array(phases)) = fields.get_mut("phaseSequence") {
+            for phase in phases.iter_mut().filter_map(Value::as_object_mut) {
+                to_seconds(phase, "minutes", "seconds");
+            }
+        }
+    },
+];
+pub const SETTINGS_VERSION: u32 = 3;
+const _: () = assert!(MIGRATIONS.len() == SETTINGS_VERSION as usize);
+
 static CFG_DIR: OnceLock<Option<ProjectDirs>> = OnceLock::new();
 static DEF_THEME: OnceLock<JsonThemeTemp> = OnceLock::new();
 
@@ -498,7 +1124,7 @@ pub fn is_wayland() -> bool {
     false
 }
 
-fn get_dir() -> Option<&'static Path> {
+pub fn get_dir() -> Option<&'static Path> {
     if let Some(dirs) = CFG_DIR.get_or_init(|| ProjectDirs::from("org", "Vadoola", "Tomotroid")) {
         Some(dirs.config_dir())
     } else {
@@ -506,6 +1132,14 @@ fn get_dir() -> Option<&'static Path> {
     }
 }
 
+//Where things Tomotroid keeps track of itself go, like the session history, rather than settings
+pub fn data_dir() -> Option<&'static Path> {
+    CFG_DIR
+        .get_or_init(|| ProjectDirs::from("org", "Vadoola", "Tomotroid"))
+        .as_ref()
+        .map(ProjectDirs::data_dir)
+}
+
 pub fn default_theme() -> &'static JsonThemeTemp {
     DEF_THEME.get_or_init(|| {
         let def_theme = r##"{
@@ -533,33 +1167,315 @@ pub fn default_theme() -> &'static JsonThemeTemp {
 //so that if no settings and/or no theme files are found it has a fallback
 //would it make any sense to use something like Figment(https://crates.io/crates/figment) instead of
 //just looking at the raw Json? Could it provide any benefit or flexibility?
-//pub fn load_settings() -> Settings {
-pub fn load_settings() -> JsonSettings {
-    //if reading the files fails use default settings
-    //need to start adding some logging probably
-    //actually probably need to restructure this a bit
-    //if the cfg dir doesn't exist, need to load defaults,
-    //then if reading the file from the dir doesn't exist
-    //need to load defualts
-    if let Some(cfg_dir) = get_dir() {
-        let file = cfg_dir.join("preferences.json");
-        if let Ok(set_file) = File::open(file) {
-            let reader = BufReader::new(set_file);
-            serde_json::from_reader(reader).expect("To be able to load the settings from json")
-        } else {
-            default_settings()
+//
+//If the file can't be loaded it gets recovered as best it can, and the second value is a message
+//explaining what happened to show the user.
+pub fn load_settings() -> (JsonSettings, Option<String>) {
+    //if the cfg dir doesn't exist or there is no settings file yet, just use the defaults
+    let Some(cfg_dir) = get_dir() else {
+        return (default_settings(), None);
+    };
+    let file = cfg_dir.join(PREFS_FILE);
+    let Ok(contents) = std::fs::read_to_string(&file) else {
+        return (default_settings(), None);
+    };
+
+    let mut value: Value = match serde_json::from_str(&contents) {
+        Ok(value) => value,
+        Err(e) => return recover_settings(&file, &partial_json(&contents), &e.to_string()),
+    };
+
+    let migrated = migrate_settings(&file, &mut value);
+    fill_defaults(&mut value, &default_settings_json());
+
+    match serde_json::from_value::<JsonSettings>(value.clone()) {
+        Ok(settings) => {
+            if migrated {
+                //already logged, and the migrated settings are still good to use this run
+                let _ = save_settings(&settings);
+            }
+            let notice = from_newer_version(&value).then(|| {
+                "Your settings are from a newer version of Tomotroid. They're being used as far as \
+                 they can be, but changes won't be saved so nothing it added gets lost."
+                    .to_string()
+            });
+            (settings, notice)
+        }
+        Err(e) => recover_settings(&file, &value, &e.to_string()),
+    }
+}
+
+//Moves the unreadable file out of the way so it isn't lost, then keeps whatever parts of it are
+//still valid on top of the defaults and writes that out as the new settings.
+fn recover_settings(file: &Path, value: &Value, reason: &str) -> (JsonSettings, Option<String>) {
+    error!(
+        "Unable to load the settings from {}: {reason}",
+        file.display()
+    );
+
+    let mut recovered = default_settings_json();
+    let (kept, _) = salvage_fields(&mut recovered, value);
+    let settings = serde_json::from_value(recovered).unwrap_or_else(|_| default_settings());
+
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let corrupt = file.with_file_name(format!("{PREFS_FILE}.corrupt-{stamp}"));
+    let saved_as = match std::fs::rename(file, &corrupt) {
+        Ok(()) => {
+            unblock_saves();
+            let _ = save_settings(&settings);
+            format!("The original file was saved as {}.", corrupt.display())
+        }
+        Err(e) => {
+            //don't write over the only copy of it
+            error!("Unable to move the unreadable settings out of the way: {e}");
+            block_saves(
+                "the settings file couldn't be read, fix it and it will be picked up again",
+            );
+            "The original file was left as it is, changes won't be saved until it's fixed."
+                .to_string()
         }
+    };
+
+    let notice = if kept == 0 {
+        format!("Your settings couldn't be read, so the defaults are being used. {saved_as}")
     } else {
-        default_settings()
+        format!(
+            "Some of your settings couldn't be read, {kept} were kept and the rest reset to defaults. {saved_as}"
+        )
+    };
+    (settings, Some(notice))
+}
+
+//Reads as much of a broken or cut short settings file as it can, field by field, stopping at the
+//first one that can't be read. Objects inside it are read the same way, so a file that ends
+//halfway through the shortcuts still has the ones before that.
+fn partial_json(text: &str) -> Value {
+    let mut fields = Map::new();
+    let _ = partial_object(text, &mut fields);
+    Value::Object(fields)
+}
+
+//Returns how much of text the object took up, or None if it ended before the closing brace
+fn partial_object(text: &str, fields: &mut Map<String, Value>) -> Option<usize> {
+    fn skip_space(text: &str, pos: usize) -> usize {
+        text.len() - text[pos..].trim_start().len()
+    }
+    fn next_value(text: &str) -> Option<(Value, usize)> {
+        let mut values = serde_json::Deserializer::from_str(text).into_iter::<Value>();
+        let value = values.next()?.ok()?;
+        Some((value, values.byte_offset()))
+    }
+
+    let mut pos = skip_space(text, 0);
+    if !text[pos..].starts_with('{') {
+        return None;
+    }
+    pos += 1;
+    loop {
+        pos = skip_space(text, pos);
+        match text[pos..].chars().next()? {
+            '}' => return Some(pos + 1),
+            ',' => {
+                pos += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let (Value::String(key), used) = next_value(&text[pos..])? else {
+            return None;
+        };
+        pos = skip_space(text, pos + used);
+        if !text[pos..].starts_with(':') {
+            return None;
+        }
+        pos = skip_space(text, pos + 1);
+
+        if text[pos..].starts_with('{') {
+            let mut inner = Map::new();
+            let used = partial_object(&text[pos..], &mut inner);
+            fields.insert(key, Value::Object(inner));
+            pos += used?;
+        } else {
+            let (value, used) = next_value(&text[pos..])?;
+            fields.insert(key, value);
+            pos += used;
+        }
     }
 }
 
-pub fn load_themes() -> Vec<JsonTheme> {
-    let theme_dir = {
-        let mut theme_dir = std::path::PathBuf::from(get_dir().unwrap());
-        theme_dir.push("themes");
-        theme_dir
+//Copies each value from the loaded settings into the defaults one at a time, keeping it only if the
+//settings still deserialize with it in place. Returns how many were kept, and the JSON pointers of
+//the ones that weren't (including any the defaults don't have).
+fn salvage_fields(recovered: &mut Value, loaded: &Value) -> (usize, Vec<String>) {
+    fn leaves(value: &Value, pointer: &str, out: &mut Vec<(String, Value)>) {
+        match value {
+            Value::Object(fields) => {
+                for (key, val) in fields {
+                    let key = key.replace('~', "~0").replace('/', "~1");
+                    leaves(val, &format!("{pointer}/{key}"), out);
+                }
+            }
+            _ if !pointer.is_empty() => out.push((pointer.to_string(), value.clone())),
+            _ => {}
+        }
+    }
+
+    let mut loaded_leaves = Vec::new();
+    leaves(loaded, "", &mut loaded_leaves);
+
+    let mut kept = 0;
+    let mut skipped = Vec::new();
+    for (pointer, val) in loaded_leaves {
+        //anything the defaults don't have is a field we don't know about
+        let Some(slot) = recovered.pointer_mut(&pointer) else {
+            skipped.push(pointer);
+            continue;
+        };
+        let default = std::mem::replace(slot, val);
+        if serde_json::from_value::<JsonSettings>(recovered.clone()).is_ok() {
+            kept += 1;
+        } else {
+            if let Some(slot) = recovered.pointer_mut(&pointer) {
+                *slot = default;
+            }
+            skipped.push(pointer);
+        }
+    }
+    (kept, skipped)
+}
+
+//Runs every migration between the file's version and the current one. A copy of the file as it was
+//before is kept next to it, in case a migration gets something wrong. Returns true if anything ran.
+fn migrate_settings(file: &Path, value: &mut Value) -> bool {
+    let Some(fields) = value.as_object_mut() else {
+        //not much to migrate, let deserializing report what's wrong with it
+        return false;
+    };
+
+    let from = settings_version(fields);
+    if from == SETTINGS_VERSION {
+        return false;
+    }
+    if from > SETTINGS_VERSION {
+        warn!("Settings are from a newer version ({from}) of Tomotroid, loading what I can");
+        //saving would drop whatever this version doesn't know about and mark them as ours
+        block_saves(NEWER_VERSION);
+        return false;
+    }
+
+    let backup = file.with_file_name(format!("{PREFS_FILE}.v{from}.bak"));
+    if let Err(e) = std::fs::copy(file, &backup) {
+        error!("Unable to back up the settings before migrating them: {e}");
+    }
+
+    run_migrations(fields, from);
+    info!("Migrated settings from version {from} to {SETTINGS_VERSION}");
+    true
+}
+
+const NEWER_VERSION: &str =
+    "the settings are from a newer version of Tomotroid, and saving would lose what it added";
+
+fn from_newer_version(value: &Value) -> bool {
+    value
+        .as_object()
+        .is_some_and(|fields| settings_version(fields) > SETTINGS_VERSION)
+}
+
+fn settings_version(fields: &Map<String, Value>) -> u32 {
+    fields
+        .get("version")
+        .and_then(Value::as_u64)
+        .map_or(0, |v| u32::try_from(v).unwrap_or(u32::MAX))
+}
+
+fn run_migrations(fields: &mut Map<String, Value>, from: u32) {
+    for (version, migration) in (from..).zip(&MIGRATIONS[from as usize..]) {
+        migration(fields);
+        fields.insert("version".to_string(), Value::from(version + 1));
+    }
+}
+
+//Pomotroid's preferences are the version 0 format, so they go through every migration and then
+//whatever still doesn't fit is dropped in favour of the default. Returns the settings along with
+//the fields that couldn't be brought over.
+pub fn import_settings(pomotroid: &Value) -> (JsonSettings, Vec<String>) {
+    let mut value = pomotroid.clone();
+    if let Some(fields) = value.as_object_mut() {
+        run_migrations(fields, 0);
+    }
+
+    let mut imported = default_settings_json();
+    let (_, skipped) = salvage_fields(&mut imported, &value);
+    let settings = serde_json::from_value(imported).unwrap_or_else(|_| default_settings());
+    (settings, skipped)
+}
+
+//Makes the named profile the active one in the settings file, for switching from the command line.
+//If Tomotroid is already running it picks the change up from the file.
+pub fn find_profile(settings: &JsonSettings, name: &str) -> Result<usize, String> {
+    settings
+        .profiles
+        .iter()
+        .position(|profile| profile.name == name)
+        .or_else(|| {
+            settings
+                .profiles
+                .iter()
+                .position(|profile| profile.name.eq_ignore_ascii_case(name))
+        })
+        .ok_or_else(|| format!("There is no profile called {name}"))
+}
+
+//Asks the Tomotroid that's already running to switch profiles. It has the settings loaded and
+//saves them itself, so they're left alone here and the name goes in a file next to them instead.
+pub fn request_profile(name: &str) -> io::Result<()> {
+    let Some(cfg_dir) = get_dir() else {
+        return Err(io::Error::other("No settings directory"));
     };
+    std::fs::create_dir_all(cfg_dir)?;
+    std::fs::write(cfg_dir.join(PROFILE_REQUEST), name)
+}
+
+//the profile asked for by request_profile, if there is one, which only gets handled the once
+pub fn take_profile_request() -> Option<String> {
+    let file = get_dir()?.join(PROFILE_REQUEST);
+    let name = std::fs::read_to_string(&file).ok()?;
+    if let Err(e) = std::fs::remove_file(&file) {
+        warn!("Unable to remove the profile request: {e}");
+    }
+    Some(name.trim().to_string()).filter(|name| !name.is_empty())
+}
+
+//True once Tomotroid has saved settings of its own
+pub fn settings_exist() -> bool {
+    get_dir().is_some_and(|dir| dir.join(PREFS_FILE).exists())
+}
+
+//Copies anything the loaded settings are missing over from the defaults, including inside nested
+//objects like the shortcuts. Fields the defaults don't know about are left alone for serde to ignore.
+fn fill_defaults(value: &mut Value, defaults: &Value) {
+    if let (Some(fields), Some(def_fields)) = (value.as_object_mut(), defaults.as_object()) {
+        for (key, def_val) in def_fields {
+            match fields.get_mut(key) {
+                Some(val) => fill_defaults(val, def_val),
+                None => {
+                    fields.insert(key.clone(), def_val.clone());
+                }
+            }
+        }
+    }
+}
+
+pub fn theme_dir() -> Option<PathBuf> {
+    get_dir().map(|dir| dir.join("themes"))
+}
+
+pub fn load_themes() -> Vec<JsonTheme> {
+    let theme_dir = theme_dir().unwrap();
     let mut themes: Vec<JsonTheme> = WalkDir::new(theme_dir)
         .into_iter()
         .filter(|e| {
@@ -588,9 +1504,8 @@ pub fn load_themes() -> Vec<JsonTheme> {
 }
 
 //fn default_settings() -> Settings {
-fn default_settings() -> JsonSettings {
-    let def_set = include_bytes!("../assets/default-preferences.json");
-    serde_json::from_reader(&def_set[..]).unwrap()
+pub fn default_settings() -> JsonSettings {
+    serde_json::from_value(default_settings_json()).unwrap()
 
     //JsonSettings using keycode
     /*JsonSettings {
@@ -660,42 +1575,257 @@ fn default_settings() -> JsonSettings {
     }*/
 }
 
-//Use https://docs.rs/serde_json/latest/serde_json/fn.to_writer_pretty.html
-//for writing out the json when I save the settings
-
-//what's the best way to call this...calling save settings every time a setting change
-//would be the safest from the perspective of ensuring the settings are updated
-//but that could be a lot of saving the file over and over.
-//for example they change a timer slider form say 5m to 10m
-//will the slint slider trigger the callback once, saying it was changed to 10,
-//or will it trigger on every value update (ie, 6, 7, 8, 9, 10) triggering 5 updates
-//to save settings? Since the settings file is pretty small/simple, I'm not sure it's worth
-//trying to update just the value that's changed, probably just easier to rewrite the whole
-//file every time. I could just save the settings on program exit...but if the program does crash
-//the settings woin't get saved. Is there some good middle ground? Every X minutes check if there
-//is a mismatch and save the settings? But then I have the overhead of some sort of timer to
-//check every so often....Actually could I save the settings only when on the main screen somehow?
-//So if the volume is changed, it saves right away (may not be super effecient, if it triggers for
-//update of the slider and they change the volume a large amount), but if it changes something on the
-//slidover screen, ie, timer, theme, etc it only saves when the slideover goes away? The logic might be
-//a bit trickier, but might be a good middle ground of ensuring the settings get saved without
-//writing out the file quite as much.
-pub fn save_settings(settings: &JsonSettings) {
-    if let Some(cfg_dir) = get_dir() {
-        std::fs::create_dir_all(cfg_dir).unwrap();
-
-        let file = cfg_dir.join("preferences.json");
-        let set_file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(file)
-            .unwrap();
-        let writer = BufWriter::new(set_file);
-
-        serde_json::to_writer_pretty(writer, &settings)
-            .expect("To be able to write the settings back out to json");
+fn default_settings_json() -> Value {
+    let def_set = include_bytes!("../assets/default-preferences.json");
+    serde_json::from_slice(def_set).unwrap()
+}
+
+//Every setting change asks for a save, and things like the sliders fire for every step they move
+//through. Rather than rewriting the file for each one, the latest settings are held on to and only
+//written once things have been quiet for SAVE_DELAY. flush_settings writes anything still waiting
+//straight away, which needs to happen on the way out.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+thread_local! {
+    static SAVE_TIMER: Timer = Timer::default();
+    static PENDING_SAVE: RefCell<Option<JsonSettings>> = const { RefCell::new(None) };
+    //what the last save wrote, so the file watcher can tell our own saves apart from outside edits
+    static LAST_SAVED: RefCell<Option<String>> = const { RefCell::new(None) };
+    //Why the settings file mustn't be written over, if it mustn't. Saving would lose what's in it,
+    //so saves fail with this until the file has been read in properly or moved out of the way.
+    static SAVES_BLOCKED: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+fn block_saves(reason: &str) {
+    SAVES_BLOCKED.with(|blocked| *blocked.borrow_mut() = Some(reason.to_string()));
+}
+
+fn unblock_saves() {
+    SAVES_BLOCKED.with(|blocked| blocked.borrow_mut().take());
+}
+
+pub fn queue_save(handle: Weak<Main>, settings: JsonSettings) {
+    PENDING_SAVE.with(|pending| *pending.borrow_mut() = Some(settings));
+
+    //starting the timer again while it's already running pushes the save back
+    SAVE_TIMER.with(|timer| {
+        timer.start(slint::TimerMode::SingleShot, SAVE_DELAY, move || {
+            if let Err(e) = flush_settings() {
+                if let Some(main) = handle.upgrade() {
+                    main.set_notice(format!("Unable to save your settings: {e}").into());
+                }
+            }
+        });
+    });
+}
+
+pub fn flush_settings() -> io::Result<()> {
+    SAVE_TIMER.with(Timer::stop);
+    match PENDING_SAVE.with(|pending| pending.borrow_mut().take()) {
+        Some(settings) => save_settings(&settings),
+        None => Ok(()),
+    }
+}
+
+//The settings are written to a temporary file first, then renamed over the real one. That way
+//a crash part way through writing leaves the old file intact instead of a half written one.
+pub fn save_settings(settings: &JsonSettings) -> io::Result<()> {
+    if let Some(reason) = SAVES_BLOCKED.with(|blocked| blocked.borrow().clone()) {
+        return Err(io::Error::other(reason));
+    }
+    let Some(cfg_dir) = get_dir() else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(cfg_dir)?;
+
+    let file = cfg_dir.join(PREFS_FILE);
+    let tmp_file = cfg_dir.join(format!("{PREFS_FILE}.tmp"));
+
+    let json = serde_json::to_string_pretty(&settings)?;
+    let result = File::create(&tmp_file).and_then(|set_file| {
+        let mut writer = BufWriter::new(set_file);
+        writer.write_all(json.as_bytes())?;
+        writer.flush()?;
+        writer.get_ref().sync_all()
+    });
+    let result = result.and_then(|()| std::fs::rename(&tmp_file, &file));
+
+    match &result {
+        Ok(()) => LAST_SAVED.with(|saved| *saved.borrow_mut() = Some(json)),
+        Err(e) => {
+            error!("Unable to save the settings to {}: {e}", file.display());
+            let _ = std::fs::remove_file(&tmp_file);
+        }
+    }
+    result
+}
+
+//Lets the app know when the settings file changes on disk, whether that's someone editing it by hand
+//or syncing it from another machine. Saves happen in a few steps (and editors do all sorts of things)
+//so this only says that something happened, reload_settings should be called once it settles down.
+//The watcher stops when the returned value is dropped.
+pub fn watch_settings(on_change: impl Fn() + Send + 'static) -> notify::Result<RecommendedWatcher> {
+    let Some(cfg_dir) = get_dir() else {
+        return Err(notify::Error::generic("No settings directory to watch"));
+    };
+    std::fs::create_dir_all(cfg_dir)?;
+
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) => {
+                let prefs_changed = event.paths.iter().any(|path| {
+                    path.file_name()
+                        .is_some_and(|name| name == PREFS_FILE || name == PROFILE_REQUEST)
+                });
+                if prefs_changed && !event.kind.is_access() {
+                    on_change();
+                }
+            }
+            Err(e) => error!("Error watching the settings file: {e}"),
+        })?;
+    //saving renames a new file over the old one, so watch the directory rather than the file itself
+    watcher.watch(cfg_dir, RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
+
+//Reads the settings for the command line, without ever writing, backing up or moving the file.
+//Older settings are only migrated in memory, and a file that can't be read is just an error.
+pub fn read_settings() -> Result<JsonSettings, String> {
+    let Some(file) = get_dir().map(|dir| dir.join(PREFS_FILE)) else {
+        return Ok(default_settings());
+    };
+    let Ok(contents) = std::fs::read_to_string(&file) else {
+        return Ok(default_settings());
+    };
+
+    let mut value: Value = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+    migrate_in_memory(&mut value);
+    fill_defaults(&mut value, &default_settings_json());
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+//Brings older settings up to date without backing up the file first, for reads that mustn't
+//leave anything behind on disk
+fn migrate_in_memory(value: &mut Value) {
+    if let Some(fields) = value.as_object_mut() {
+        let from = settings_version(fields);
+        if from < SETTINGS_VERSION {
+            run_migrations(fields, from);
+        }
+    }
+}
+
+//Reads the settings back in after the file changed on disk. Unlike load_settings this never rewrites
+//or moves the file, it's probably still being edited, so anything wrong with it is just reported and
+//the current settings are kept until it's fixed. Returns None if the file is just our own last save.
+pub fn reload_settings() -> Result<Option<JsonSettings>, String> {
+    let Some(cfg_dir) = get_dir() else {
+        return Ok(None);
+    };
+    let file = cfg_dir.join(PREFS_FILE);
+    let contents = std::fs::read_to_string(&file).map_err(|e| e.to_string())?;
+
+    if LAST_SAVED.with(|saved| saved.borrow().as_deref() == Some(contents.as_str())) {
+        return Ok(None);
+    }
+
+    let mut value: Value = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+    migrate_in_memory(&mut value);
+    fill_defaults(&mut value, &default_settings_json());
+    let newer = from_newer_version(&value);
+    let settings = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    if newer {
+        block_saves(NEWER_VERSION);
+    } else {
+        //whatever was wrong with the file, it's been fixed
+        unblock_saves();
+    }
+
+    //anything still waiting to be saved is older than the edit, writing it now would undo the edit
+    SAVE_TIMER.with(Timer::stop);
+    PENDING_SAVE.with(|pending| pending.borrow_mut().take());
+    Ok(Some(settings))
+}
+
+//anything longer than a day is almost certainly a typo
+const MAX_DURATION: i32 = 24 * 60 * 60;
+
+//Reads a duration typed into the timer page, returning it in seconds. Takes "25:00" or "1:15:00",
+//units like "90s", "25m" or "1h15m", and a plain number is taken as minutes like the sliders.
+pub fn parse_duration(text: &str) -> Option<i32> {
+    let text: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase();
+    if text.is_empty() {
+        return None;
+    }
+
+    let seconds: u64 = if text.contains(':') {
+        let parts = text
+            .split(':')
+            .map(|part| part.parse::<u32>().ok().map(u64::from))
+            .collect::<Option<Vec<u64>>>()?;
+        match parts[..] {
+            [m, s] if s < 60 => m * 60 + s,
+            [h, m, s] if m < 60 && s < 60 => h * 3600 + m * 60 + s,
+            _ => return None,
+        }
+    } else if text.chars().all(|c| c.is_ascii_digit()) {
+        u64::from(text.parse::<u32>().ok()?) * 60
+    } else {
+        let mut total = 0;
+        let mut number = String::new();
+        for c in text.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let unit = match c {
+                'h' => 3600,
+                'm' => 60,
+                's' => 1,
+                _ => return None,
+            };
+            total += u64::from(number.parse::<u32>().ok()?) * unit;
+            number.clear();
+        }
+        //a number left over without a unit, like "1h15"
+        if !number.is_empty() {
+            return None;
+        }
+        total
+    };
+
+    i32::try_from(seconds)
+        .ok()
+        .filter(|seconds| (1..=MAX_DURATION).contains(seconds))
+}
+
+//Builds the same "Control+Alt+X" style string the settings file uses from a Slint key event, so it
+//can be shown in the config page and parsed back into a JsonHotKey
+pub fn hotkey_text(mods: Modifiers, text: &SharedString) -> String {
+    let mut hk_text = String::new();
+    if mods.ctrl() {
+        hk_text.push_str("Control+");
+    }
+    if mods.alt() {
+        hk_text.push_str("Alt+");
+    }
+    if mods.shift() {
+        hk_text.push_str("Shift+");
+    }
+    if mods.meta() {
+        hk_text.push_str("Super+");
+    }
+
+    if let Some(non_pr_char) = get_non_print_key_txt(text) {
+        hk_text.push_str(non_pr_char);
+    } else {
+        hk_text.push_str(&text.to_uppercase());
     }
+    hk_text
 }
 
 pub fn get_non_print_key_txt(text: &SharedString) -> Option<&'static str> {
@@ -726,14 +1856,16 @@ pub fn get_non_print_key_txt(text: &SharedString) -> Option<&'static str> {
         Some("Del")
     } else if *text == SharedString::from(Key::CapsLock) {
         Some("CapsLk")
+    } else if *text == SharedString::from(Key::Space) {
+        Some("Space")
     } else if *text == SharedString::from(Key::UpArrow) {
-        Some("↑")
+        Some("Up")
     } else if *text == SharedString::from(Key::DownArrow) {
-        Some("↓")
+        Some("Down")
     } else if *text == SharedString::from(Key::LeftArrow) {
-        Some("→")
+        Some("Left")
     } else if *text == SharedString::from(Key::RightArrow) {
-        Some("←")
+        Some("Right")
     } else if *text == SharedString::from(Key::F1) {
         Some("F1")
     } else if *text == SharedString::from(Key::F2) {
@@ -880,37 +2012,7 @@ pub fn bool_changed(
             );
         }
 
-        match set_type {
-            BoolSettTypes::AlwOnTop => {
-                handle.global::<Settings>().set_always_on_top(!val);
-            }
-            BoolSettTypes::AutoStrtBreakTim => {
-                handle.global::<Settings>().set_auto_start_break_timer(!val);
-            }
-            BoolSettTypes::AutoStrtWrkTim => {
-                handle.global::<Settings>().set_auto_start_work_timer(!val);
-            }
-            BoolSettTypes::BrkAlwOnTop => {
-                handle.global::<Settings>().set_break_always_on_top(!val);
-            }
-            BoolSettTypes::MinToTray => {
-                handle.global::<Settings>().set_min_to_tray(!val);
-            }
-            BoolSettTypes::MinToTryCls => {
-                handle.global::<Settings>().set_min_to_tray_on_close(!val);
-            }
-            BoolSettTypes::Notifications => {
-                handle.global::<Settings>().set_notifications(!val);
-            }
-            BoolSettTypes::TickSounds => {
-                handle.global::<Settings>().set_tick_sounds(!val);
-            }
-            BoolSettTypes::TickSoundsBreak => {
-                handle
-                    .global::<Settings>()
-                    .set_tick_sounds_during_break(!val);
-            }
-        }
+        set_bool(&handle, set_type, !val);
         //write out settings?...not the most effecient way every change..but for now should be fine
         handle.save_settings();
     } else {
@@ -918,6 +2020,57 @@ pub fn bool_changed(
     }
 }
 
+//Puts the new value of one of the config page toggles into the Settings global
+fn set_bool(handle: &Main, set_type: BoolSettTypes, val: bool) {
+    match set_type {
+        BoolSettTypes::AlwOnTop => {
+            handle.global::<Settings>().set_always_on_top(val);
+        }
+        BoolSettTypes::AutoStrtBreakTim => {
+            handle.global::<Settings>().set_auto_start_break_timer(val);
+        }
+        BoolSettTypes::AutoStrtWrkTim => {
+            handle.global::<Settings>().set_auto_start_work_timer(val);
+        }
+        BoolSettTypes::BrkAlwOnTop => {
+            handle.global::<Settings>().set_break_always_on_top(val);
+        }
+        BoolSettTypes::MinToTray => {
+            handle.global::<Settings>().set_min_to_tray(val);
+        }
+        BoolSettTypes::MinToTryCls => {
+            handle.global::<Settings>().set_min_to_tray_on_close(val);
+        }
+        BoolSettTypes::Notifications => {
+            handle.global::<Settings>().set_notifications(val);
+        }
+        BoolSettTypes::TickSounds => {
+            handle.global::<Settings>().set_tick_sounds(val);
+        }
+        BoolSettTypes::TickSoundsBreak => {
+            handle
+                .global::<Settings>()
+                .set_tick_sounds_during_break(val);
+        }
+        BoolSettTypes::Overtime => {
+            handle.global::<Settings>().set_overtime(val);
+        }
+        BoolSettTypes::StopAtTarget => {
+            handle.global::<Settings>().set_stop_at_target(val);
+        }
+        BoolSettTypes::GoalInMinutes => {
+            handle.global::<Settings>().set_goal_in_minutes(val);
+        }
+        BoolSettTypes::Reflection => {
+            handle.global::<Settings>().set_reflection(val);
+        }
+        BoolSettTypes::StrictBreaks => {
+            handle.global::<Settings>().set_strict_breaks(val);
+            crate::overlay::sync(handle);
+        }
+    }
+}
+
 pub fn int_changed(handle: &Weak<Main>, vol_sink: &Rc<Sink>, set_type: IntSettTypes, val: i32) {
     let handle = handle.upgrade().unwrap();
     match set_type {
@@ -937,6 +2090,34 @@ pub fn int_changed(handle: &Weak<Main>, vol_sink: &Rc<Sink>, set_type: IntSettTy
         IntSettTypes::Rounds => {
             handle.global::<Settings>().set_work_rounds(val);
         }
+        IntSettTypes::SnoozeLimit => {
+            handle.global::<Settings>().set_snooze_limit(val);
+        }
+        IntSettTypes::ExtendBy => {
+            handle.global::<Settings>().set_extend_by(val);
+        }
+        IntSettTypes::DailyRounds => {
+            handle.global::<Settings>().set_daily_rounds(val);
+        }
+        IntSettTypes::DailyMinutes => {
+            handle.global::<Settings>().set_daily_minutes(val);
+        }
+        IntSettTypes::IdleThreshold => {
+            handle.global::<Settings>().set_idle_threshold(val);
+        }
+        IntSettTypes::StrictSkipAfter => {
+            handle.global::<Settings>().set_strict_skip_after(val);
+        }
+        IntSettTypes::StrictSkipLimit => {
+            handle.global::<Settings>().set_strict_skip_limit(val);
+        }
+        IntSettTypes::MicroBreakEvery => {
+            handle.global::<Settings>().set_micro_break_every(val);
+        }
+        IntSettTypes::DayStart => {
+            handle.global::<Settings>().set_day_start(val);
+            handle.refresh_today();
+        }
     }
 
     //write out settings?...not the most effecient way every change..but for now should be fine
@@ -954,9 +2135,13 @@ fn color_to_hex_string(color: slint::Color) -> String {
 
 pub fn theme_changed(handle: &Weak<Main>, idx: i32, theme: JsonTheme) {
     let handle = handle.upgrade().unwrap();
-    handle.global::<Settings>().set_theme(theme.name);
+    handle.global::<Settings>().set_theme(theme.name.clone());
     handle.save_settings();
+    apply_theme(&handle, idx, theme);
+}
 
+//Updates the colours and logo without touching the saved settings
+pub fn apply_theme(handle: &Main, idx: i32, theme: JsonTheme) {
     handle.set_logo(
         slint::Image::load_from_svg_data(
             LOGO_BYTES
@@ -1008,3 +2193,239 @@ pub fn theme_changed(handle: &Weak<Main>, idx: i32, theme: JsonTheme) {
         .set_foreground_darkest(theme.foreground_darkest);
     handle.global::<Theme>().set_accent(theme.accent);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated_settings_keep_the_fields_before_the_cut() {
+        let text = r#"{
+    "version": 3,
+    "alwaysOnTop": true,
+    "globalShortcuts": {
+        "call-timer-reset": "Control+F9",
+        "call-timer-sk"#;
+        let loaded = partial_json(text);
+        let mut recovered = default_settings_json();
+        let (kept, _) = salvage_fields(&mut recovered, &loaded);
+
+        assert_eq!(kept, 3);
+        let settings: JsonSettings = serde_json::from_value(recovered).unwrap();
+        assert!(settings.always_on_top);
+        assert_eq!(
+            settings.global_shortcuts.reset,
+            JsonHotKey::from_str("Control+F9").unwrap()
+        );
+    }
+
+    #[test]
+    fn malformed_settings_stop_at_the_bad_field() {
+        let loaded = partial_json(r#"{"volume": 40, "theme": nope, "workRounds": 2}"#);
+        assert_eq!(loaded, serde_json::json!({"volume": 40}));
+    }
+
+    #[test]
+    fn settings_that_are_not_an_object_give_nothing() {
+        assert_eq!(partial_json("[1, 2"), serde_json::json!({}));
+    }
+
+    #[test]
+    fn blocked_saves_fail_with_the_reason() {
+        block_saves("the file is broken");
+        let result = save_settings(&default_settings());
+        unblock_saves();
+        assert_eq!(result.unwrap_err().to_string(), "the file is broken");
+    }
+
+    #[test]
+    fn settings_from_a_newer_version_are_not_saved_over() {
+        let mut value = serde_json::json!({"version": SETTINGS_VERSION + 1});
+        let migrated =
+            migrate_settings(Path::new("nowhere").join(PREFS_FILE).as_path(), &mut value);
+        let result = save_settings(&default_settings());
+        unblock_saves();
+
+        assert!(!migrated);
+        assert_eq!(value, serde_json::json!({"version": SETTINGS_VERSION + 1}));
+        assert_eq!(result.unwrap_err().to_string(), NEWER_VERSION);
+    }
+
+    fn migrate(from: u32, value: Value) -> Value {
+        let Value::Object(mut fields) = value else {
+            unreachable!()
+        };
+        MIGRATIONS[from as usize](&mut fields);
+        Value::Object(fields)
+    }
+
+    #[test]
+    fn migration_0_to_1_changes_nothing() {
+        let value = serde_json::json!({"timeWork": 25, "volume": 40});
+        assert_eq!(migrate(0, value.clone()), value);
+    }
+
+    #[test]
+    fn migration_1_to_2_makes_the_timer_the_first_profile() {
+        let migrated = migrate(
+            1,
+            serde_json::json!({"timeWork": 30, "workRounds": 3, "volume": 40}),
+        );
+        assert_eq!(
+            migrated,
+            serde_json::json!({
+                "timeWork": 30,
+                "workRounds": 3,
+                "volume": 40,
+                "profiles": [{"name": "Default", "timeWork": 30, "workRounds": 3}],
+                "activeProfile": "Default",
+            })
+        );
+    }
+
+    #[test]
+    fn migration_2_to_3_turns_minutes_into_seconds() {
+        let migrated = migrate(
+            2,
+            serde_json::json!({
+                "timeWork": 25,
+                "timeShortBreak": 5,
+                "profiles": [{"name": "Default", "timeLongBreak": 15}],
+                "phaseSequence": [{"name": "Focus", "minutes": 50}],
+            }),
+        );
+        assert_eq!(
+            migrated,
+            serde_json::json!({
+                "timeWork": 1500,
+                "timeShortBreak": 300,
+                "profiles": [{"name": "Default", "timeLongBreak": 900}],
+                "phaseSequence": [{"name": "Focus", "seconds": 3000}],
+            })
+        );
+    }
+
+    #[test]
+    fn migrations_stamp_each_version() {
+        let mut value = serde_json::json!({"timeWork": 25});
+        migrate_in_memory(&mut value);
+        assert_eq!(value["version"], SETTINGS_VERSION);
+        assert_eq!(value["timeWork"], 1500);
+    }
+
+    #[test]
+    fn missing_nested_fields_come_from_the_defaults() {
+        let mut value = serde_json::json!({
+            "volume": 40,
+            "globalShortcuts": {"call-timer-reset": "Control+F9"},
+            "somethingNewer": true,
+        });
+        fill_defaults(&mut value, &default_settings_json());
+
+        assert_eq!(value["volume"], 40);
+        assert_eq!(value["globalShortcuts"]["call-timer-reset"], "Control+F9");
+        assert_eq!(value["globalShortcuts"]["call-timer-skip"], "Control+F3");
+        assert_eq!(value["localShortcuts"]["timer-toggle"], "Space");
+        assert_eq!(value["somethingNewer"], true);
+    }
+
+    #[test]
+    fn typed_durations_are_read() {
+        for (text, seconds) in [
+            ("25:00", Some(25 * 60)),
+            ("1:15:00", Some(75 * 60)),
+            ("0:45", Some(45)),
+            ("90s", Some(90)),
+            ("25m", Some(25 * 60)),
+            ("1h15m", Some(75 * 60)),
+            ("1H 15M", Some(75 * 60)),
+            ("2h", Some(2 * 3600)),
+            ("25", Some(25 * 60)),
+            (" 5 ", Some(5 * 60)),
+            ("24:00:00", Some(MAX_DURATION)),
+            ("1440", Some(MAX_DURATION)),
+        ] {
+            assert_eq!(parse_duration(text), seconds, "{text}");
+        }
+    }
+
+    #[test]
+    fn bad_durations_are_not() {
+        for text in [
+            "",
+            "   ",
+            "0",
+            "0:00",
+            "0s",
+            "abc",
+            "25x",
+            "1h15",
+            "5:60",
+            "1:60:00",
+            "1:2:3:4",
+            ":30",
+            "-5",
+            "1.5",
+            "24:00:01",
+            "1441",
+            "25h",
+            "99999999999",
+        ] {
+            assert_eq!(parse_duration(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn steps_without_time_are_left_out() {
+        let mut value = default_settings_json();
+        value["phaseSequence"] = serde_json::json!([
+            {"name": "Focus", "seconds": 1500, "focus": true},
+            {"name": "Nothing", "seconds": 0},
+            {"name": "Break", "seconds": -5},
+        ]);
+        let settings: JsonSettings = serde_json::from_value(value).unwrap();
+        let names: Vec<&str> = settings
+            .phase_sequence
+            .iter()
+            .map(|phase| phase.name.as_str())
+            .collect();
+        assert_eq!(names, ["Focus"]);
+    }
+
+    #[test]
+    fn typed_steps_are_read() {
+        let focus = JsonPhase::parse("Deep Work 50:00", None).unwrap();
+        assert_eq!((focus.name.as_str(), focus.seconds), ("Deep Work", 50 * 60));
+        assert!(focus.focus);
+
+        let long = JsonPhase::parse(" Long Break 30m ", None).unwrap();
+        assert_eq!(long.seconds, 30 * 60);
+        assert_eq!(long.color, JsonPhaseColor::LongRound);
+        assert!(!long.focus);
+
+        let short = JsonPhase::parse("break 5", None).unwrap();
+        assert_eq!(short.sound, JsonPhaseSound::ShortBreak);
+
+        assert_eq!(JsonPhase::parse("Focus", None), None);
+        assert_eq!(JsonPhase::parse("25:00", None), None);
+        assert_eq!(JsonPhase::parse("Focus soon", None), None);
+    }
+
+    #[test]
+    fn editing_a_step_keeps_how_it_was_set_up() {
+        let review = JsonPhase {
+            name: "Review".to_string(),
+            seconds: 15 * 60,
+            color: JsonPhaseColor::Accent,
+            sound: JsonPhaseSound::None,
+            focus: false,
+        };
+        let edited = JsonPhase::parse("review 20m", Some(&review)).unwrap();
+        assert_eq!(edited.color, JsonPhaseColor::Accent);
+        assert!(!edited.focus);
+        assert_eq!(edited.seconds, 20 * 60);
+
+        let renamed = JsonPhase::parse("Write 20m", Some(&review)).unwrap();
+        assert!(renamed.focus);
+    }
+}
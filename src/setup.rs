@@ -1,18 +1,47 @@
 #[cfg(unix)]
 use anyhow::Result;
 use log::{error, LevelFilter};
+use slint::Color;
 use std::{
+    f32::consts::TAU,
     io::Cursor,
-    sync::mpsc::{self, Receiver},
+    sync::mpsc::{self, Receiver, Sender},
+    time::{Duration, Instant},
 };
 use systemd_journal_logger::{connected_to_journal, JournalLog};
 use tray_item::{IconSource, TrayItem};
 
+#[derive(Clone, Copy)]
 pub enum TrayMsg {
     MinRes,
     Quit,
+    Toggle,
+    Reset,
+    Skip,
+    SnoozeShort,
+    SnoozeLong,
+    Extend,
+    Mute,
+    NextProfile,
 }
 
+//What the UI thread sends back to the tray so the menu can follow the timer
+pub struct TrayStatus {
+    pub phase: String,
+    pub remaining: String,
+    pub running: bool,
+    pub muted: bool,
+    pub progress: f32,
+    pub color: Color,
+    pub profile: String,
+    //the extend item's label, since how long it extends by can be changed
+    pub extend: String,
+}
+
+const ICON_SIZE: u16 = 32;
+//the ring only needs redrawing every few seconds, a 32px icon won't visibly change faster than that
+const ICON_REFRESH: Duration = Duration::from_secs(5);
+
 pub fn logging() {
     #[cfg(unix)] //is this true for Mac? how would I test for non-mac *nix?
     let fallback_needed = if connected_to_journal() {
@@ -56,22 +85,126 @@ pub fn logging() {
 //because I don't see any way to capture when the tray icon is clicked
 //I'll need to dig into this more. For now I'll just add some menu items
 //to get some basic functionality and test minimzing to the tray etc
-pub fn tray() -> Result<Receiver<TrayMsg>> {
+pub fn tray() -> Result<(Receiver<TrayMsg>, Sender<TrayStatus>)> {
     let mut tray = create_tray()?;
 
     let (tray_tx, tray_rx) = mpsc::sync_channel(1);
 
-    let minres_tx = tray_tx.clone();
-    tray.add_menu_item("Minimize / Restore", move || {
-        minres_tx.send(TrayMsg::MinRes).unwrap();
-    })?;
+    //Windows has proper labels that can be changed later. tray-item's ksni labels never get an id
+    //to change them with, so on Linux the status line is a menu item that does nothing when clicked.
+    #[cfg(windows)]
+    let status_id = tray.inner_mut().add_label_with_id("Focus")?;
+    #[cfg(not(windows))]
+    let status_id = tray.inner_mut().add_menu_item_with_id("Focus", || {})?;
+    tray.inner_mut().add_separator()?;
 
-    let quit_tx = tray_tx;
-    tray.add_menu_item("Quit", move || {
-        quit_tx.send(TrayMsg::Quit).unwrap();
-    })?;
+    let menu_item = |msg: TrayMsg| {
+        let tx = tray_tx.clone();
+        move || {
+            if tx.send(msg).is_err() {
+                error!("Tray message sent after the UI stopped listening");
+            }
+        }
+    };
+
+    let toggle_id = tray
+        .inner_mut()
+        .add_menu_item_with_id("Start", menu_item(TrayMsg::Toggle))?;
+    tray.add_menu_item("Reset", menu_item(TrayMsg::Reset))?;
+    tray.add_menu_item("Skip", menu_item(TrayMsg::Skip))?;
+    tray.add_menu_item("Snooze 2 Minutes", menu_item(TrayMsg::SnoozeShort))?;
+    tray.add_menu_item("Snooze 5 Minutes", menu_item(TrayMsg::SnoozeLong))?;
+    let extend_id = tray
+        .inner_mut()
+        .add_menu_item_with_id("Extend", menu_item(TrayMsg::Extend))?;
+    let mute_id = tray
+        .inner_mut()
+        .add_menu_item_with_id("Mute", menu_item(TrayMsg::Mute))?;
+    //no submenus in tray-item either, so clicking the profile just moves on to the next one
+    let profile_id = tray
+        .inner_mut()
+        .add_menu_item_with_id("Profile", menu_item(TrayMsg::NextProfile))?;
+    tray.inner_mut().add_separator()?;
+
+    tray.add_menu_item("Minimize / Restore", menu_item(TrayMsg::MinRes))?;
+    tray.add_menu_item("Quit", menu_item(TrayMsg::Quit))?;
+
+    //The tray has to stay alive for the menu to keep working, so hand it off to a thread that owns it
+    //and relabels the menu whenever the UI sends a new status. Once the UI drops its sender the thread
+    //ends and the tray goes with it.
+    let (status_tx, status_rx) = mpsc::channel::<TrayStatus>();
+    std::thread::spawn(move || {
+        let mut last_icon: Option<(Instant, Color, bool)> = None;
+        #[cfg(windows)]
+        let mut prev_hicon = None;
 
-    Ok(tray_rx)
+        while let Ok(status) = status_rx.recv() {
+            //redraw right away when the phase colour or paused state changes, otherwise just
+            //every few seconds to follow the progress
+            let redraw = last_icon.is_none_or(|(drawn, color, running)| {
+                color != status.color
+                    || running != status.running
+                    || drawn.elapsed() >= ICON_REFRESH
+            });
+            if redraw {
+                let rgba = render_icon(status.progress, status.color, !status.running);
+                match icon_source(&rgba) {
+                    Ok(icon) => {
+                        if let Err(e) = tray.inner_mut().set_icon(icon.clone()) {
+                            error!("Unable to update the tray icon: {e}");
+                        }
+                        #[cfg(windows)]
+                        if let IconSource::RawIcon(hicon) = icon {
+                            //the tray has its own copy now, so the previous icon can go
+                            if let Some(prev) = prev_hicon.replace(hicon) {
+                                destroy_icon(prev);
+                            }
+                        }
+                    }
+                    Err(e) => error!("Unable to create the tray icon: {e}"),
+                }
+                last_icon = Some((Instant::now(), status.color, status.running));
+            }
+
+            let status_text = format!("{} - {}", status.phase, status.remaining);
+            let toggle_text = if status.running { "Pause" } else { "Start" };
+            let mute_text = if status.muted { "Unmute" } else { "Mute" };
+            let profile_text = format!("Profile: {}", status.profile);
+
+            #[cfg(windows)]
+            let status_updated = tray.inner_mut().set_label(&status_text, status_id);
+            #[cfg(not(windows))]
+            let status_updated = tray
+                .inner_mut()
+                .set_menu_item_label(&status_text, status_id);
+            let updated = status_updated
+                .and_then(|()| tray.inner_mut().set_menu_item_label(toggle_text, toggle_id))
+                .and_then(|()| tray.inner_mut().set_menu_item_label(mute_text, mute_id))
+                .and_then(|()| {
+                    tray.inner_mut()
+                        .set_menu_item_label(&status.extend, extend_id)
+                })
+                .and_then(|()| {
+                    tray.inner_mut()
+                        .set_menu_item_label(&profile_text, profile_id)
+                });
+            if let Err(e) = updated {
+                error!("Unable to update the tray menu: {e}");
+            }
+
+            //Only Windows gets the countdown as a tooltip. tray-item's ksni tray never sets a tooltip
+            //and keeps its ksni handle private, so the status line in the menu has to do on Linux.
+            #[cfg(windows)]
+            if let Err(e) = tray
+                .inner_mut()
+                .set_tooltip(&format!("Tomotroid\n{status_text}"))
+            {
+                error!("Unable to update the tray tooltip: {e}");
+            }
+        }
+    });
+
+    Ok((tray_rx, status_tx))
 }
 
 #[cfg(unix)]
@@ -106,6 +239,100 @@ fn create_tray() -> Result<TrayItem> {
     )
 }
 
+//Draws the same ring the CircularProgress component does, starting at the top and running clockwise
+//with the remaining fraction in the phase colour. Pause bars go in the middle when the timer is stopped.
+//Returns straight (non-premultiplied) RGBA rows, top to bottom.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] //alpha is clamped to 0..=1 first
+fn render_icon(progress: f32, color: Color, paused: bool) -> Vec<u8> {
+    const OUTER: f32 = 15.0;
+    const INNER: f32 = 11.0;
+    const TRACK_ALPHA: f32 = 0.3;
+
+    let centre = f32::from(ICON_SIZE) / 2.0;
+    let mut rgba = Vec::with_capacity(usize::from(ICON_SIZE) * usize::from(ICON_SIZE) * 4);
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let dx = f32::from(x) + 0.5 - centre;
+            let dy = f32::from(y) + 0.5 - centre;
+            let dist = dx.hypot(dy);
+
+            //soften the edges of the ring by how far the pixel centre is from them
+            let ring = (OUTER - dist + 0.5).clamp(0.0, 1.0) * (dist - INNER + 0.5).clamp(0.0, 1.0);
+            let angle = dx.atan2(-dy).rem_euclid(TAU) / TAU;
+            let mut alpha = if angle < progress {
+                ring
+            } else {
+                ring * TRACK_ALPHA
+            };
+
+            if paused && dy.abs() < 5.0 && (1.5..4.5).contains(&dx.abs()) {
+                alpha = 1.0;
+            }
+
+            rgba.extend_from_slice(&[
+                color.red(),
+                color.green(),
+                color.blue(),
+                (alpha * 255.0).round() as u8,
+            ]);
+        }
+    }
+    rgba
+}
+
+//ksni wants ARGB32 in network byte order
+#[cfg(unix)]
+#[allow(clippy::unnecessary_wraps)] //creating the icon can fail on Windows
+fn icon_source(rgba: &[u8]) -> Result<IconSource> {
+    let argb = rgba
+        .chunks_exact(4)
+        .flat_map(|px| [px[3], px[0], px[1], px[2]])
+        .collect();
+
+    Ok(IconSource::Data {
+        data: argb,
+        height: i32::from(ICON_SIZE),
+        width: i32::from(ICON_SIZE),
+    })
+}
+
+#[cfg(windows)]
+fn icon_source(rgba: &[u8]) -> Result<IconSource> {
+    use windows_sys::Win32::UI::WindowsAndMessaging::CreateIcon;
+
+    let bgra: Vec<u8> = rgba
+        .chunks_exact(4)
+        .flat_map(|px| [px[2], px[1], px[0], px[3]])
+        .collect();
+    //with a 32bit colour bitmap the alpha channel does the masking, so the AND mask can be empty
+    let and_mask = vec![0u8; usize::from(ICON_SIZE) * usize::from(ICON_SIZE) / 8];
+
+    // SAFETY: both buffers are sized for a 32x32 icon at the bit depths passed in
+    let hicon = unsafe {
+        CreateIcon(
+            0,
+            i32::from(ICON_SIZE),
+            i32::from(ICON_SIZE),
+            1,
+            32,
+            and_mask.as_ptr(),
+            bgra.as_ptr(),
+        )
+    };
+    if hicon == 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(IconSource::RawIcon(hicon))
+}
+
+#[cfg(windows)]
+fn destroy_icon(hicon: windows_sys::Win32::UI::WindowsAndMessaging::HICON) {
+    // SAFETY: only icons created by icon_source end up here, and only once the tray has replaced them
+    unsafe {
+        windows_sys::Win32::UI::WindowsAndMessaging::DestroyIcon(hicon);
+    }
+}
+
 pub fn backend() {
     let backend = {
         #[cfg(target_os = "macos")]